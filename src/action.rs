@@ -30,4 +30,9 @@ pub enum Action {
     ToggleMouseCapture,
     /// Adds to the velocity of the camera.
     AddCameraVelocity(f64),
+    /// Zooms the camera in (positive) or out (negative). The value is the number of times the
+    /// zoom doubles (or halves) per second.
+    Zoom(f64),
+    /// Saves the current frame to an image file.
+    SaveScreenshot,
 }
@@ -1,17 +1,20 @@
 //! Exposes the API that will be used to create an interactable window that can be drawn on.
 
-use std::time::{Duration, Instant};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::u32;
 
 use crate::action::Action;
+use crate::graphics::image_export::ImageFormat;
+use crate::graphics::render_target::RenderTarget;
 use crate::pipeline::shader::{Shader, ShaderType};
-use crate::pipeline::Pipeline;
+use crate::pipeline::{Msaa, Pipeline};
 use crate::{
     graphics::{self, screen::Screen, window::Window},
     inputs,
     scene::{self, Scene},
 };
-use glam::DVec2;
+use glam::{DVec2, DVec3};
 use winit::application::ApplicationHandler;
 use winit::event::{DeviceEvent, ElementState};
 use winit::event_loop::ControlFlow;
@@ -43,6 +46,8 @@ pub struct SoftwareRenderer {
     last_fps_count_time: Instant,
     /// The number of frames that were rendered since the last fps count.
     frame_count: u32,
+    /// The format used when saving screenshots.
+    screenshot_format: ImageFormat,
 }
 impl SoftwareRenderer {
     /// Creates an app.
@@ -54,17 +59,18 @@ impl SoftwareRenderer {
     /// * `width` - Width of the window.
     /// * `height` - Height of the window.
     /// * `scene` - The scene that will be rendered.
-    /// * ``
+    /// * `shader` - The shader used to render the scene.
+    /// * `msaa` - Number of samples per pixel used to smooth the edges of the triangles.
     ///
     /// # Returns
     ///
     /// The instantiated App.
-    pub fn new(width: usize, height: usize, scene: Scene, shader: Shader) -> Self {
+    pub fn new(width: usize, height: usize, scene: Scene, shader: Shader, msaa: Msaa) -> Self {
         let window = Window::new(width, height);
         let input_state = inputs::InputHandler::new();
         let screen = Screen::new(width, height);
         let fps = 144;
-        let pipeline = Pipeline::new(64, width, height, shader);
+        let pipeline = Pipeline::new(64, width, height, shader, msaa);
         let last_frame_time = Instant::now();
         let last_fps_count = Instant::now();
 
@@ -82,6 +88,7 @@ impl SoftwareRenderer {
             cur_it: 0,
             last_fps_count_time: last_fps_count,
             frame_count,
+            screenshot_format: ImageFormat::Png,
         }
     }
     /// Creates an app.
@@ -98,8 +105,8 @@ impl SoftwareRenderer {
     /// The instantiated App.
     pub fn with_default_scene(width: usize, height: usize) -> Self {
         let scene = Scene::new();
-        let shader = Shader::new(0.15, ShaderType::Flat);
-        Self::new(width, height, scene, shader)
+        let shader = Shader::new(DVec3::splat(0.15), ShaderType::Flat);
+        Self::new(width, height, scene, shader, Msaa::Off)
     }
     /// Acts on actions.
     ///
@@ -145,9 +152,35 @@ impl SoftwareRenderer {
                 Action::AddCameraVelocity(velocity) => {
                     self.scene.camera_mut().add_velocity(*velocity);
                 }
+                Action::Zoom(speed) => {
+                    let camera = self.scene.camera_mut();
+                    camera.zoom(2f64.powf(*speed / self.fps as f64));
+                }
+                Action::SaveScreenshot => {
+                    self.save_screenshot();
+                }
             }
         }
     }
+    /// Saves the last rendered frame in the working directory.
+    ///
+    /// The file is named after the time at which it was taken and uses the screenshot format of
+    /// the app.
+    fn save_screenshot(&self) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let path = PathBuf::from(format!(
+            "screenshot_{}.{}",
+            timestamp,
+            self.screenshot_format.extension()
+        ));
+        match self.screen.save_color(&path, self.screenshot_format) {
+            Ok(()) => println!("Screenshot saved to {}", path.display()),
+            Err(e) => eprintln!("Could not save screenshot: {e}"),
+        }
+    }
     /// Captures or release the mouse from the app.
     pub fn capture_mouse(&mut self, capture: bool) {
         let winit_window = self.window.winit_window_mut();
@@ -210,6 +243,14 @@ impl SoftwareRenderer {
     pub fn set_max_it(&mut self, max_it: u64) {
         self.max_it = max_it;
     }
+    /// Sets the format used when saving screenshots.
+    pub fn set_screenshot_format(&mut self, screenshot_format: ImageFormat) {
+        self.screenshot_format = screenshot_format;
+    }
+    /// Obtains a mutable reference to the pipeline, to add post effects for example.
+    pub fn pipeline_mut(&mut self) -> &mut Pipeline {
+        &mut self.pipeline
+    }
 }
 
 impl ApplicationHandler for SoftwareRenderer {
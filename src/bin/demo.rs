@@ -7,8 +7,16 @@ use glam::{DQuat, DVec3, U8Vec3};
 // println!("{}, {}", v.x, a[(1, 2)]);
 use soft_rend::{
     app::SoftwareRenderer,
-    pipeline::shader::{Shader, ShaderType},
-    resources::loaders::{DefaultMesh, DefaultTexture, MeshLoader, TextureLoader},
+    pipeline::{
+        post_process::Vignette,
+        shader::{Shader, ShaderType},
+        Msaa,
+    },
+    resources::{
+        loaders::{DefaultMesh, DefaultTexture, MeshLoader, TextureLoader},
+        material::Material,
+        sampler::{Filter, Sampler},
+    },
     scene::{
         camera::{Camera, CameraStyle},
         light::{Light, LightType},
@@ -61,7 +69,21 @@ fn main() -> Result<(), winit::error::EventLoopError> {
             print!("The texture could not be added to the scene: {}", e);
             0
         });
-    cube1.set_texture(Some(checkered_id));
+    let checkered_material_id = scene
+        .material_catalog_mut()
+        .add_material(
+            String::from("Checkered"),
+            Material {
+                // Smooth out the magnified texture.
+                sampler: Some(Sampler::new(Filter::Bilinear)),
+                ..Material::from_texture(checkered_id)
+            },
+        )
+        .unwrap_or_else(|e| {
+            print!("The material could not be added to the scene: {}", e);
+            0
+        });
+    cube1.set_material(Some(checkered_material_id));
     // Create a wall of cubes.
     let side = 5;
     let moves = 110.0;
@@ -74,21 +96,25 @@ fn main() -> Result<(), winit::error::EventLoopError> {
     }
 
     // Add lights.
-    let light = Light::new(
+    let mut light = Light::new(
         1.5,
         U8Vec3::new(255, 255, 255),
         LightType::AtInfinity(DVec3::new(0.2, -0.2, -1.0)),
     );
+    light.casts_shadows = true;
     scene.add_light(light);
     // Decide on the type of shader.
     let shader_type = ShaderType::Flat;
     let shader = match shader_type {
-        ShaderType::Phong => todo!("Implement Phong shader"),
-        ShaderType::Gouraud => todo!("Implement Gouraud shader"),
-        ShaderType::Flat => Shader::new(0.15, ShaderType::Flat),
+        ShaderType::Phong => Shader::new(DVec3::splat(0.15), ShaderType::Phong),
+        ShaderType::Gouraud => Shader::new(DVec3::splat(0.15), ShaderType::Gouraud),
+        ShaderType::Flat => Shader::new(DVec3::splat(0.15), ShaderType::Flat),
     };
-    // Create and start the app.
-    let mut software_renderer = SoftwareRenderer::new(width, height, scene, shader);
+    // Create and start the app, smoothing the edges with 4 samples per pixel.
+    let mut software_renderer = SoftwareRenderer::new(width, height, scene, shader, Msaa::X4);
+    software_renderer
+        .pipeline_mut()
+        .add_post_effect(Vignette::new(0.4));
     // app.set_max_it(30);
     event_loop.run_app(&mut software_renderer)?;
     Ok(())
@@ -0,0 +1,101 @@
+//! Conversions between the sRGB color space, used by images and screens, and the linear color
+//! space, where light adds up correctly.
+use std::sync::LazyLock;
+
+use glam::{DVec4, U8Vec4};
+
+/// Linear value of each 8 bit sRGB value.
+static SRGB_TO_LINEAR: LazyLock<[f64; 256]> =
+    LazyLock::new(|| std::array::from_fn(|value| srgb_to_linear(value as f64 / 255.0)));
+/// Linear values halfway (in sRGB) between consecutive 8 bit sRGB values. The closest 8 bit
+/// sRGB value of a linear value is the number of thresholds it reaches.
+static LINEAR_TO_SRGB_THRESHOLDS: LazyLock<[f64; 255]> =
+    LazyLock::new(|| std::array::from_fn(|value| srgb_to_linear((value as f64 + 0.5) / 255.0)));
+
+/// Decodes an 8 bit sRGB value to a linear value between 0-1.
+#[inline(always)]
+pub fn decode_srgb(value: u8) -> f64 {
+    SRGB_TO_LINEAR[value as usize]
+}
+/// Encodes a linear value to the closest 8 bit sRGB value. The value is clamped between 0-1
+/// first.
+#[inline(always)]
+pub fn encode_srgb(value: f64) -> u8 {
+    LINEAR_TO_SRGB_THRESHOLDS.partition_point(|&threshold| threshold <= value) as u8
+}
+/// Decodes an 8 bit sRGBA color.
+///
+/// # Arguments
+///
+/// * `color` - The color (RGBA). Only the color channels are sRGB encoded, alpha is linear.
+///
+/// # Return
+///
+/// The linear color (RGBA), with each channel between 0-1.
+#[inline(always)]
+pub fn decode_srgb_color(color: U8Vec4) -> DVec4 {
+    DVec4::new(
+        decode_srgb(color.x),
+        decode_srgb(color.y),
+        decode_srgb(color.z),
+        color.w as f64 / 255.0,
+    )
+}
+/// Encodes a linear color to 8 bit sRGBA.
+///
+/// # Arguments
+///
+/// * `color` - The linear color (RGBA). Each channel is clamped between 0-1.
+///
+/// # Return
+///
+/// The color with its color channels sRGB encoded, and its alpha left linear.
+#[inline(always)]
+pub fn encode_srgb_color(color: DVec4) -> U8Vec4 {
+    U8Vec4::new(
+        encode_srgb(color.x),
+        encode_srgb(color.y),
+        encode_srgb(color.z),
+        (color.w.clamp(0.0, 1.0) * 255.0).round() as u8,
+    )
+}
+/// Converts an sRGB value between 0-1 to linear.
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Converts a linear value between 0-1 to sRGB.
+    fn linear_to_srgb(value: f64) -> f64 {
+        if value <= 0.0031308 {
+            value * 12.92
+        } else {
+            1.055 * value.powf(1.0 / 2.4) - 0.055
+        }
+    }
+
+    #[test]
+    fn encode_srgb_gives_closest_value() {
+        for step in 0..=200_000 {
+            let linear = step as f64 / 200_000.0;
+            let expected = (linear_to_srgb(linear) * 255.0).round() as u8;
+            assert_eq!(encode_srgb(linear), expected, "linear value {linear}");
+        }
+        assert_eq!(encode_srgb(0.995545), 254);
+        assert_eq!(encode_srgb(-1.0), 0);
+        assert_eq!(encode_srgb(2.0), 255);
+    }
+
+    #[test]
+    fn decode_then_encode_is_identity() {
+        for value in 0..=255 {
+            assert_eq!(encode_srgb(decode_srgb(value)), value);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! In-memory render target. Allows rendering without a window, event loop or GPU.
+use super::render_target::RenderTarget;
+
+/// Plain RGBA color buffer and depth buffer living in memory.
+pub struct FrameBuffer {
+    /// Width of the buffer.
+    width: usize,
+    /// Height of the buffer.
+    height: usize,
+    /// The RGBA pixel values. Left to right, top to bottom.
+    color: Vec<u8>,
+    /// The depth value of every pixel. Left to right, top to bottom.
+    depth: Vec<f64>,
+    /// Background color.
+    bg_color: [u8; 4],
+}
+
+impl FrameBuffer {
+    /// Creates a new frame buffer.
+    ///
+    /// The color buffer is filled with the background color and the depth buffer with the maximum
+    /// depth.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - Width of the buffer (in pixels).
+    /// * `height` - Height of the buffer (in pixels).
+    ///
+    /// # Returns
+    ///
+    /// The instantiated FrameBuffer.
+    pub fn new(width: usize, height: usize) -> Self {
+        let bg_color = [42, 0, 23, 255];
+        FrameBuffer {
+            width,
+            height,
+            color: bg_color.repeat(width * height),
+            depth: vec![f64::INFINITY; width * height],
+            bg_color,
+        }
+    }
+}
+// Getters and setters.
+impl FrameBuffer {
+    /// Reference to the RGBA pixel values.
+    pub fn color(&self) -> &[u8] {
+        &self.color
+    }
+    /// Reference to the depth values.
+    pub fn depth(&self) -> &[f64] {
+        &self.depth
+    }
+    /// Reference to the background color.
+    pub fn bg_color(&self) -> &[u8] {
+        &self.bg_color
+    }
+    /// Sets the background color.
+    pub fn set_bg_color(&mut self, bg_color: [u8; 4]) {
+        self.bg_color = bg_color;
+    }
+}
+impl RenderTarget for FrameBuffer {
+    fn width(&self) -> usize {
+        self.width
+    }
+    fn height(&self) -> usize {
+        self.height
+    }
+    fn color_buffer(&self) -> Option<&[u8]> {
+        Some(&self.color)
+    }
+    fn color_buffer_mut(&mut self) -> Option<&mut [u8]> {
+        Some(&mut self.color)
+    }
+    fn depth_buffer(&self) -> Option<&[f64]> {
+        Some(&self.depth)
+    }
+    fn depth_buffer_mut(&mut self) -> Option<&mut [f64]> {
+        Some(&mut self.depth)
+    }
+}
@@ -0,0 +1,393 @@
+//! Encodes rendered frames into image files (PNG, binary PPM and BMP).
+//!
+//! The encoders are deliberately simple. PNG data is stored without compression, which makes the
+//! files bigger but keeps the crate free of compression dependencies.
+use std::io;
+
+/// The image formats a frame can be saved as.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Portable Network Graphics. Colors are saved as 8 bit RGBA, depth as 16 bit grayscale.
+    Png,
+    /// Binary Netpbm. Colors are saved as 8 bit RGB (P6), depth as 16 bit grayscale (P5).
+    Ppm,
+    /// Windows bitmap. Colors are saved as 8 bit RGB. Depth is not supported.
+    Bmp,
+}
+impl ImageFormat {
+    /// The file extension usually associated with the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Bmp => "bmp",
+        }
+    }
+}
+
+/// Encodes an RGBA color buffer into an image file.
+///
+/// # Arguments
+///
+/// * `width` - Width of the image (in pixels).
+/// * `height` - Height of the image (in pixels).
+/// * `rgba` - The RGBA pixel values. Left to right, top to bottom.
+/// * `format` - The format of the image file.
+///
+/// # Return
+///
+/// The bytes of the image file.
+///
+/// # Errors
+///
+/// Returns an error of kind [`io::ErrorKind::InvalidInput`] if the image is empty or if `rgba`
+/// does not contain `width * height` pixels.
+pub fn encode_color(
+    width: usize,
+    height: usize,
+    rgba: &[u8],
+    format: ImageFormat,
+) -> io::Result<Vec<u8>> {
+    if width == 0 || height == 0 || rgba.len() != width * height * 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Expected {} bytes of color data, got {}.",
+                width * height * 4,
+                rgba.len()
+            ),
+        ));
+    }
+    Ok(match format {
+        ImageFormat::Png => {
+            // Each row starts with its filter type (0 = none).
+            let mut raw = Vec::with_capacity(height * (width * 4 + 1));
+            for row in rgba.chunks_exact(width * 4) {
+                raw.push(0);
+                raw.extend_from_slice(row);
+            }
+            encode_png(width, height, 8, 6, &raw)
+        }
+        ImageFormat::Ppm => {
+            let mut file = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+            for pixel in rgba.chunks_exact(4) {
+                file.extend_from_slice(&pixel[..3]);
+            }
+            file
+        }
+        ImageFormat::Bmp => encode_bmp(width, height, rgba),
+    })
+}
+
+/// Encodes a depth buffer into a 16 bit grayscale image file.
+///
+/// The finite depth values are normalized such that the closest one is black and the farthest one
+/// is white. Infinite values (pixels where nothing was drawn) are white.
+///
+/// # Arguments
+///
+/// * `width` - Width of the image (in pixels).
+/// * `height` - Height of the image (in pixels).
+/// * `depth` - The depth values. Left to right, top to bottom.
+/// * `format` - The format of the image file.
+///
+/// # Return
+///
+/// The bytes of the image file.
+///
+/// # Errors
+///
+/// Returns an error of kind [`io::ErrorKind::InvalidInput`] if the image is empty or if `depth`
+/// does not contain `width * height` values, and of kind [`io::ErrorKind::Unsupported`] if the
+/// format cannot hold a 16 bit grayscale image.
+pub fn encode_depth(
+    width: usize,
+    height: usize,
+    depth: &[f64],
+    format: ImageFormat,
+) -> io::Result<Vec<u8>> {
+    if width == 0 || height == 0 || depth.len() != width * height {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Expected {} depth values, got {}.",
+                width * height,
+                depth.len()
+            ),
+        ));
+    }
+    // Find the range of the depth values that were drawn.
+    let (min, max) = depth
+        .iter()
+        .filter(|d| d.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &d| {
+            (min.min(d), max.max(d))
+        });
+    let range = if max > min { max - min } else { 1.0 };
+    // Normalize them to big endian 16 bit values.
+    let mut samples = Vec::with_capacity(width * height * 2);
+    for &d in depth {
+        let value = if d.is_finite() {
+            (((d - min) / range) * u16::MAX as f64).round() as u16
+        } else {
+            u16::MAX
+        };
+        samples.extend_from_slice(&value.to_be_bytes());
+    }
+    match format {
+        ImageFormat::Png => {
+            let mut raw = Vec::with_capacity(height * (width * 2 + 1));
+            for row in samples.chunks_exact(width * 2) {
+                raw.push(0);
+                raw.extend_from_slice(row);
+            }
+            Ok(encode_png(width, height, 16, 0, &raw))
+        }
+        ImageFormat::Ppm => {
+            let mut file = format!("P5\n{} {}\n65535\n", width, height).into_bytes();
+            file.extend_from_slice(&samples);
+            Ok(file)
+        }
+        ImageFormat::Bmp => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "BMP files cannot hold 16 bit grayscale images.",
+        )),
+    }
+}
+
+/// Wraps filtered scanlines into a PNG file.
+///
+/// # Arguments
+///
+/// * `width` - Width of the image (in pixels).
+/// * `height` - Height of the image (in pixels).
+/// * `bit_depth` - Number of bits per channel.
+/// * `color_type` - PNG color type (0 for grayscale, 6 for RGBA).
+/// * `raw` - The scanlines, each one preceded by its filter type.
+fn encode_png(width: usize, height: usize, bit_depth: u8, color_type: u8, raw: &[u8]) -> Vec<u8> {
+    let mut file = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+    // Image header.
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // Bit depth, color type, compression, filter and interlace methods.
+    header.extend_from_slice(&[bit_depth, color_type, 0, 0, 0]);
+    write_png_chunk(&mut file, b"IHDR", &header);
+    write_png_chunk(&mut file, b"IDAT", &zlib_stored(raw));
+    write_png_chunk(&mut file, b"IEND", &[]);
+    file
+}
+
+/// Appends a PNG chunk (length, type, data and crc) to the file.
+fn write_png_chunk(file: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) {
+    file.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let crc_start = file.len();
+    file.extend_from_slice(chunk_type);
+    file.extend_from_slice(data);
+    let crc = crc32(&file[crc_start..]);
+    file.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps data into a zlib stream made of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // Stored blocks can hold at most u16::MAX bytes.
+    let max_block = u16::MAX as usize;
+    let mut stream = Vec::with_capacity(data.len() + data.len() / max_block * 5 + 11);
+    // Deflate compression with a 32K window, no preset dictionary.
+    stream.extend_from_slice(&[0x78, 0x01]);
+    let nb_blocks = data.len().div_ceil(max_block).max(1);
+    for block in 0..nb_blocks {
+        let start = block * max_block;
+        let end = (start + max_block).min(data.len());
+        let len = (end - start) as u16;
+        // Only the last block has its BFINAL bit set.
+        stream.push(if block == nb_blocks - 1 { 1 } else { 0 });
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(&data[start..end]);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+/// Computes the CRC-32 (ISO-HDLC) of the data, as required by PNG chunks.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Computes the Adler-32 checksum of the data, as required by zlib streams.
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/// Encodes an RGBA color buffer as a 24 bit BMP file.
+fn encode_bmp(width: usize, height: usize, rgba: &[u8]) -> Vec<u8> {
+    // Rows are padded to a multiple of 4 bytes.
+    let row_size = (width * 3).div_ceil(4) * 4;
+    let data_offset = 14 + 40;
+    let file_size = data_offset + row_size * height;
+    let mut file = Vec::with_capacity(file_size);
+    // File header.
+    file.extend_from_slice(b"BM");
+    file.extend_from_slice(&(file_size as u32).to_le_bytes());
+    file.extend_from_slice(&[0; 4]);
+    file.extend_from_slice(&(data_offset as u32).to_le_bytes());
+    // Info header.
+    file.extend_from_slice(&40u32.to_le_bytes());
+    file.extend_from_slice(&(width as i32).to_le_bytes());
+    // Negative height means the rows are stored top to bottom.
+    file.extend_from_slice(&(-(height as i32)).to_le_bytes());
+    file.extend_from_slice(&1u16.to_le_bytes());
+    file.extend_from_slice(&24u16.to_le_bytes());
+    // No compression, image size, resolution (2835 pixels/m ~ 72 dpi) and palette.
+    file.extend_from_slice(&0u32.to_le_bytes());
+    file.extend_from_slice(&((row_size * height) as u32).to_le_bytes());
+    file.extend_from_slice(&2835u32.to_le_bytes());
+    file.extend_from_slice(&2835u32.to_le_bytes());
+    file.extend_from_slice(&[0; 8]);
+    // Pixels are stored as BGR.
+    for row in rgba.chunks_exact(width * 4) {
+        for pixel in row.chunks_exact(4) {
+            file.extend_from_slice(&[pixel[2], pixel[1], pixel[0]]);
+        }
+        file.resize(file.len() + row_size - width * 3, 0);
+    }
+    file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Splits a PNG file (after its signature) into its chunks: type, data and crc.
+    fn png_chunks(file: &[u8]) -> Vec<([u8; 4], &[u8], u32)> {
+        let mut chunks = Vec::new();
+        let mut rest = &file[8..];
+        while !rest.is_empty() {
+            let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+            let chunk_type = rest[4..8].try_into().unwrap();
+            let data = &rest[8..8 + len];
+            let crc = u32::from_be_bytes(rest[8 + len..12 + len].try_into().unwrap());
+            chunks.push((chunk_type, data, crc));
+            rest = &rest[12 + len..];
+        }
+        chunks
+    }
+
+    #[test]
+    fn crc32_matches_known_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+    }
+
+    #[test]
+    fn adler32_matches_known_values() {
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"123456789"), 0x091E_01DE);
+        // Long enough for the sums to be reduced several times.
+        let data = vec![0xFF; 100_000];
+        let (mut a, mut b) = (1u32, 0u32);
+        for &byte in &data {
+            a = (a + byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        assert_eq!(adler32(&data), (b << 16) | a);
+    }
+
+    #[test]
+    fn png_has_signature_and_chunk_layout() {
+        let rgba: Vec<u8> = (0..3 * 2 * 4).map(|value| value as u8).collect();
+        let file = encode_color(3, 2, &rgba, ImageFormat::Png).unwrap();
+        assert_eq!(
+            file[..8],
+            [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n']
+        );
+        let chunks = png_chunks(&file);
+        let types: Vec<_> = chunks.iter().map(|(chunk_type, _, _)| chunk_type).collect();
+        assert_eq!(types, [b"IHDR", b"IDAT", b"IEND"]);
+        for (chunk_type, data, crc) in &chunks {
+            assert_eq!(crc32(&[chunk_type.as_slice(), data].concat()), *crc);
+        }
+        // Width, height, bit depth, color type (RGBA), compression, filter and interlace.
+        let header = chunks[0].1;
+        assert_eq!(header.len(), 13);
+        assert_eq!(header[..4], 3u32.to_be_bytes());
+        assert_eq!(header[4..8], 2u32.to_be_bytes());
+        assert_eq!(header[8..], [8, 6, 0, 0, 0]);
+        // A single stored block holding each row preceded by its filter type.
+        let stream = chunks[1].1;
+        let mut raw = vec![0];
+        raw.extend_from_slice(&rgba[..12]);
+        raw.push(0);
+        raw.extend_from_slice(&rgba[12..]);
+        assert_eq!(stream[..3], [0x78, 0x01, 1]);
+        assert_eq!(stream[3..5], (raw.len() as u16).to_le_bytes());
+        assert_eq!(stream[5..7], (!(raw.len() as u16)).to_le_bytes());
+        assert_eq!(stream[7..stream.len() - 4], raw);
+        assert_eq!(stream[stream.len() - 4..], adler32(&raw).to_be_bytes());
+        assert!(chunks[2].1.is_empty());
+    }
+
+    #[test]
+    fn depth_png_is_16_bit_grayscale() {
+        let depth = [1.0, 2.0, f64::INFINITY, 3.0];
+        let file = encode_depth(2, 2, &depth, ImageFormat::Png).unwrap();
+        let header = png_chunks(&file)[0].1;
+        assert_eq!(header[8..], [16, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn zlib_splits_long_data_into_stored_blocks() {
+        let data = vec![7; u16::MAX as usize + 10];
+        let stream = zlib_stored(&data);
+        // The first block is full and not final, the second one holds the rest.
+        assert_eq!(stream[2..5], [0, 0xFF, 0xFF]);
+        let second = 2 + 5 + u16::MAX as usize;
+        assert_eq!(stream[second..second + 3], [1, 10, 0]);
+        assert_eq!(stream.len(), 2 + 2 * 5 + data.len() + 4);
+    }
+
+    #[test]
+    fn bmp_rows_are_padded_for_odd_widths() {
+        let rgba = [
+            [1, 2, 3, 255],
+            [4, 5, 6, 255],
+            [7, 8, 9, 255],
+            [10, 11, 12, 255],
+            [13, 14, 15, 255],
+            [16, 17, 18, 255],
+        ]
+        .concat();
+        let file = encode_bmp(3, 2, &rgba);
+        // 9 bytes of pixels padded to 12 per row.
+        assert_eq!(file.len(), 54 + 2 * 12);
+        assert_eq!(file[2..6], (file.len() as u32).to_le_bytes());
+        assert_eq!(file[18..22], 3i32.to_le_bytes());
+        assert_eq!(file[22..26], (-2i32).to_le_bytes());
+        assert_eq!(file[28..30], 24u16.to_le_bytes());
+        assert_eq!(file[34..38], (2 * 12u32).to_le_bytes());
+        // Rows are stored top to bottom, in BGR.
+        assert_eq!(file[54..66], [3, 2, 1, 6, 5, 4, 9, 8, 7, 0, 0, 0]);
+        assert_eq!(file[66..78], [12, 11, 10, 15, 14, 13, 18, 17, 16, 0, 0, 0]);
+        // A single pixel is padded to 4 bytes.
+        assert_eq!(encode_bmp(1, 1, &[1, 2, 3, 255])[54..], [3, 2, 1, 0]);
+    }
+}
@@ -1,3 +1,7 @@
 //! Hosts the API to handle screen drawing and window creation.
 pub mod window;
 pub mod screen;
+pub mod render_target;
+pub mod frame_buffer;
+pub mod image_export;
+pub mod color;
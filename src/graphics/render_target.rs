@@ -0,0 +1,70 @@
+//! Abstraction over the buffers a rendered frame can be written into.
+use std::{fs, io, path::Path};
+
+use super::image_export::{self, ImageFormat};
+
+/// Something the [`crate::pipeline::Pipeline`] can write a rendered frame into.
+///
+/// The color buffer holds RGBA pixels (4 bytes per pixel), left to right, top to bottom. The depth
+/// buffer, when there is one, holds a single value per pixel in the same order. Smaller depth
+/// values are closer to the camera.
+pub trait RenderTarget {
+    /// Width (in pixels) of the target.
+    fn width(&self) -> usize;
+    /// Height (in pixels) of the target.
+    fn height(&self) -> usize;
+    /// Reference to the RGBA color buffer. None if it is not available (yet).
+    fn color_buffer(&self) -> Option<&[u8]>;
+    /// Mutable reference to the RGBA color buffer. None if it is not available (yet).
+    fn color_buffer_mut(&mut self) -> Option<&mut [u8]>;
+    /// Reference to the depth buffer. None if the target does not keep depth values.
+    fn depth_buffer(&self) -> Option<&[f64]> {
+        None
+    }
+    /// Mutable reference to the depth buffer. None if the target does not keep depth values.
+    fn depth_buffer_mut(&mut self) -> Option<&mut [f64]> {
+        None
+    }
+    /// Saves the color buffer to an image file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to write.
+    /// * `format` - Format of the image file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the color buffer is not available, if it cannot be encoded or if the
+    /// file cannot be written.
+    fn save_color(&self, path: &Path, format: ImageFormat) -> io::Result<()> {
+        let Some(color) = self.color_buffer() else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "The color buffer is not available.",
+            ));
+        };
+        let file = image_export::encode_color(self.width(), self.height(), color, format)?;
+        fs::write(path, file)
+    }
+    /// Saves the depth buffer to a normalized 16 bit grayscale image file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file to write.
+    /// * `format` - Format of the image file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the target has no depth buffer, if the format does not support 16 bit
+    /// grayscale images or if the file cannot be written.
+    fn save_depth(&self, path: &Path, format: ImageFormat) -> io::Result<()> {
+        let Some(depth) = self.depth_buffer() else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "The render target does not keep depth values.",
+            ));
+        };
+        let file = image_export::encode_depth(self.width(), self.height(), depth, format)?;
+        fs::write(path, file)
+    }
+}
@@ -6,6 +6,8 @@ use winit;
 
 use crate::resources::texture::Texture;
 
+use super::render_target::RenderTarget;
+
 /// Contains the necessary information to draw pixels on the screen.
 pub struct Screen {
     /// Width of the buffer.
@@ -88,10 +90,7 @@ impl Screen {
         let width = self.width;
         let height = self.height;
         let frame = self.pixels_mut().unwrap().frame_mut();
-        let nb_channels = match texture.format() {
-            crate::resources::texture::Format::RGBA32 => 4,
-            crate::resources::texture::Format::RGB24 => 3,
-        };
+        let nb_channels = texture.format().nb_channels();
         for row in 0..height {
             for col in 0..width {
                 let (u, v) = (col as f64 / width as f64, row as f64 / height as f64);
@@ -124,3 +123,17 @@ impl Screen {
         &self.bg_color
     }
 }
+impl RenderTarget for Screen {
+    fn width(&self) -> usize {
+        self.width
+    }
+    fn height(&self) -> usize {
+        self.height
+    }
+    fn color_buffer(&self) -> Option<&[u8]> {
+        self.pixels.as_ref().map(|pixels| pixels.frame())
+    }
+    fn color_buffer_mut(&mut self) -> Option<&mut [u8]> {
+        self.pixels.as_mut().map(|pixels| pixels.frame_mut())
+    }
+}
@@ -1,7 +1,8 @@
 //! Handles the input from the user.
 //!
 //! Use WASD to move around the environment and the mouse to look around. TAB is used to
-//! capture/release the mouse within the window.
+//! capture/release the mouse within the window. The + and - keys zoom in and out. F12 saves the
+//! current frame to an image file.
 use crate::action::Action;
 use glam::DVec2;
 use std::collections::HashMap;
@@ -240,7 +241,10 @@ impl InputHandler {
         self.held_action.insert(KeyCode::Space, Action::MoveUp);
         self.held_action
             .insert(KeyCode::ControlLeft, Action::MoveDown);
+        self.held_action.insert(KeyCode::Equal, Action::Zoom(1.0));
+        self.held_action.insert(KeyCode::Minus, Action::Zoom(-1.0));
         self.pressed_action.insert(KeyCode::Tab, Action::ToggleMouseCapture);
+        self.pressed_action.insert(KeyCode::F12, Action::SaveScreenshot);
     }
     /// Define the amount of times the mouse has been scrolled.
     /// Positive means scroll up, negative means scroll down.
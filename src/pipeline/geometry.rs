@@ -2,19 +2,38 @@
 //! related to geometry.
 use std::collections::HashMap;
 
-use glam::{usize, DMat4, DVec2, DVec3, DVec4, Vec4Swizzles};
+use glam::{usize, DMat3, DMat4, DVec2, DVec3, DVec4, Vec4Swizzles};
 
 use crate::{algorithm, resources::mesh::Mesh};
 
+use super::shader::{Lighting, ViewPoint};
+
 /// Contains the necessary information to draw shapes on screen.
 #[derive(Clone)]
 pub struct Geometry {
-    /// The id of the texture.
-    texture_id: Option<u32>,
+    /// The id of the material.
+    material_id: Option<u32>,
     /// Homogeneous position of the vertices making up the shape.
     vertices: Vec<DVec4>,
     /// UV coordinates of the vertices.
     uvs: Vec<DVec2>,
+    /// Colors (RGBA) of the vertices.
+    colors: Vec<DVec4>,
+    /// Normals of the vertices. Only in world space once [`Geometry::transform_normals`] has been
+    /// called with the mesh's world transform.
+    normals: Vec<DVec3>,
+    /// Tangents of the vertices (xyz) and the sign of their bitangent (w). Transformed along with
+    /// the normals.
+    tangents: Vec<DVec4>,
+    /// Positions of the vertices in world space. Only set once [`Geometry::set_world_positions`]
+    /// has been called, empty otherwise.
+    world_positions: Vec<DVec3>,
+    /// Lighting of each vertex. Only set when the lighting is evaluated per vertex
+    /// (Gouraud shading), empty otherwise.
+    vertex_shading: Vec<Lighting>,
+    /// Light reaching each vertex from each light with a shadow map, before the shadows are
+    /// applied. The values of a vertex follow each other. Set along with `vertex_shading`.
+    vertex_shadowed_shading: Vec<Lighting>,
     /// The list of indices that define the triangles in the mesh. Each successive 3 idex represent
     /// a triangle. (Defined CCW)
     triangles: Vec<usize>,
@@ -36,13 +55,22 @@ impl Geometry {
     pub fn new(
         vertices: &Vec<DVec4>,
         uvs: &Vec<DVec2>,
+        colors: &[DVec4],
+        normals: &[DVec3],
+        tangents: &[DVec4],
         triangles: &Vec<usize>,
-        texture_id: Option<u32>,
+        material_id: Option<u32>,
     ) -> Self {
         Geometry {
-            texture_id: texture_id,
+            material_id,
             vertices: vertices.clone(),
             uvs: uvs.clone(),
+            colors: colors.to_vec(),
+            normals: normals.to_vec(),
+            tangents: tangents.to_vec(),
+            world_positions: Vec::new(),
+            vertex_shading: Vec::new(),
+            vertex_shadowed_shading: Vec::new(),
             triangles: triangles.clone(),
             clip_w_inv: vec![1.0; vertices.len()],
             triangle_normals: Vec::with_capacity(vertices.len() / 3),
@@ -52,18 +80,30 @@ impl Geometry {
     pub fn from_mesh(mesh: &Mesh) -> Self {
         let mut vertices = Vec::new();
         let mut uvs = Vec::new();
+        let mut colors = Vec::new();
+        let mut normals = Vec::new();
+        let mut tangents = Vec::new();
         let triangles = mesh.triangles().clone();
         // Populate the vectors.
         for vec in mesh.vertices() {
             vertices.push(*vec.position());
             uvs.push(*vec.uv());
+            colors.push(*vec.color());
+            normals.push(*vec.normal());
+            tangents.push(*vec.tangent());
         }
         let nb_triangles = vertices.len() / 3;
         Geometry {
-            texture_id: mesh.texture_id(),
+            material_id: mesh.material_id(),
             clip_w_inv: Vec::new(),
             vertices,
             uvs,
+            colors,
+            normals,
+            tangents,
+            world_positions: Vec::new(),
+            vertex_shading: Vec::new(),
+            vertex_shadowed_shading: Vec::new(),
             triangles,
             triangle_normals: Vec::with_capacity(nb_triangles),
         }
@@ -76,6 +116,35 @@ impl Geometry {
         }
     }
 
+    /// Transforms the normals and tangents of the vertices given the transform that was applied
+    /// to the vertices' positions.
+    ///
+    /// Normals are transformed with the inverse transpose of the transform's linear part, which
+    /// keeps them perpendicular to the surface under non uniform scaling. Tangents lie on the
+    /// surface, so they are transformed like positions. Both are normalized afterwards.
+    pub fn transform_normals(&mut self, transform: &DMat4) {
+        let linear_transform = DMat3::from_mat4(*transform);
+        let normal_transform = linear_transform.inverse().transpose();
+        for normal in self.normals.iter_mut() {
+            *normal = normal_transform.mul_vec3(*normal).normalize_or_zero();
+        }
+        // Mirroring transforms flip the bitangents.
+        let handedness = linear_transform.determinant().signum();
+        for tangent in self.tangents.iter_mut() {
+            let direction = linear_transform.mul_vec3(tangent.xyz()).normalize_or_zero();
+            *tangent = direction.extend(tangent.w * handedness);
+        }
+    }
+
+    /// Saves the current position of the vertices as their world space position.
+    ///
+    /// Needs to be called when the geometry is in world space. The world positions are then
+    /// interpolated along with the other vertex attributes during clipping, which allows the
+    /// lighting to be computed anywhere on the triangles.
+    pub fn set_world_positions(&mut self) {
+        self.world_positions = self.vertices.iter().map(|v| v.xyz()).collect();
+    }
+
     /// Divide every position by its perspective value w, which is the fourth value in the position
     /// vector. This is called perspective division and is an important part of the rendering
     /// process that allows us to go from clip space to ndc space.
@@ -92,8 +161,34 @@ impl Geometry {
     /// Needs to be called when the geoemtry is in world space. If in camera space, use
     /// camera_position = 0.
     ///
+    /// Only valid for perspective projections. Use [`Geometry::cull_backface_orthographic`] for
+    /// orthographic ones.
+    ///
     /// * `camera_position` - The camera position in world space.
     pub fn cull_backface(&mut self, camera_position: &DVec3) {
+        self.retain_front_faces(|a, tri_face_normal| {
+            // Vector from camera to first vertex of triangle.
+            let cam_to_tri = a - camera_position;
+            cam_to_tri.dot(tri_face_normal) < 0.0
+        });
+    }
+    /// Given the direction the camera is looking at, cull every triangle pointing away from it.
+    /// Needs to be called when the geoemtry is in world space.
+    ///
+    /// Under an orthographic projection every view ray is parallel to the camera's direction, so
+    /// the position of the camera does not matter.
+    ///
+    /// * `view_direction` - The direction the camera is looking at in world space.
+    pub fn cull_backface_orthographic(&mut self, view_direction: &DVec3) {
+        self.retain_front_faces(|_, tri_face_normal| view_direction.dot(tri_face_normal) < 0.0);
+    }
+    /// Only keep the triangles that are facing the camera.
+    ///
+    /// # Arguments
+    ///
+    /// * `is_front_face` - Given the first vertex of a triangle and its (non normalized) normal,
+    ///   decides whether the triangle is facing the camera.
+    fn retain_front_faces(&mut self, is_front_face: impl Fn(DVec3, DVec3) -> bool) {
         // Create a new list of triangles which are facing towards the camera.
         let mut triangles = Vec::<usize>::with_capacity(self.triangles.len());
         // Check each triangle within the mesh and only keep those pointing towards the camera.
@@ -110,12 +205,10 @@ impl Geometry {
                 self.vertices[bi].xyz(),
                 self.vertices[ci].xyz(),
             );
-            // Vector from camera to first vertex of triangle.
-            let cam_to_tri = a - camera_position;
             // Vector normal to the triangle pointing to the exterior of the mesh.
             let tri_face_normal = (b - a).cross(c - a);
             // If the triangle is pointing towards the camera, keep it.
-            if cam_to_tri.dot(tri_face_normal) < 0.0 {
+            if is_front_face(a, tri_face_normal) {
                 triangles.push(ai);
                 triangles.push(bi);
                 triangles.push(ci);
@@ -222,14 +315,9 @@ impl Geometry {
                             // Add the intersection to the geometry.
                             // TODO: Don't add it directly to the geometry, as some intersections
                             // are later removed through other plane clipping.
-                            let c = a.lerp(b, t);
-                            self.vertices.push(c);
-
-                            let uv = self.uvs[ai].lerp(self.uvs[bi], t);
-                            self.uvs.push(uv);
+                            let ci = self.push_interpolated_vertex(ai, bi, t);
 
                             // And add it to the new shape.
-                            let ci = self.vertices.len() - 1;
                             intersection_cache.insert((e1, e2, *plane_type), ci);
                             new_shape.push(ci);
                         }
@@ -253,6 +341,75 @@ impl Geometry {
         }
         self.triangles = triangles;
     }
+    /// Adds a vertex between two existing vertices. Every vertex attribute is linearly
+    /// interpolated.
+    ///
+    /// # Arguments
+    ///
+    /// * `ai` - Index of the first vertex.
+    /// * `bi` - Index of the second vertex.
+    /// * `t` - Where the new vertex lies between the first (t=0) and the second (t=1) vertex.
+    ///
+    /// # Return
+    ///
+    /// The index of the new vertex.
+    fn push_interpolated_vertex(&mut self, ai: usize, bi: usize, t: f64) -> usize {
+        self.vertices.push(self.vertices[ai].lerp(self.vertices[bi], t));
+        self.uvs.push(self.uvs[ai].lerp(self.uvs[bi], t));
+        self.colors.push(self.colors[ai].lerp(self.colors[bi], t));
+        if !self.normals.is_empty() {
+            self.normals.push(self.normals[ai].lerp(self.normals[bi], t));
+        }
+        if !self.tangents.is_empty() {
+            self.tangents.push(self.tangents[ai].lerp(self.tangents[bi], t));
+        }
+        if !self.world_positions.is_empty() {
+            let world_position = self.world_positions[ai].lerp(self.world_positions[bi], t);
+            self.world_positions.push(world_position);
+        }
+        if !self.vertex_shading.is_empty() {
+            let nb_shadowed = self.vertex_shadowed_shading.len() / self.vertex_shading.len();
+            let vertex_shading = self.vertex_shading[ai].lerp(self.vertex_shading[bi], t);
+            self.vertex_shading.push(vertex_shading);
+            for light in 0..nb_shadowed {
+                let shadowed_shading = self.vertex_shadowed_shading[ai * nb_shadowed + light]
+                    .lerp(self.vertex_shadowed_shading[bi * nb_shadowed + light], t);
+                self.vertex_shadowed_shading.push(shadowed_shading);
+            }
+        }
+        self.vertices.len() - 1
+    }
+    /// Reorders the triangles so that the ones furthest from the viewer come first.
+    ///
+    /// Triangles are rasterized in order, so this lets transparent triangles blend over the ones
+    /// behind them. Needs the world positions (see [`Geometry::set_world_positions`]), and has to
+    /// be called before [`Geometry::set_triangle_world_normals`].
+    ///
+    /// # Arguments
+    ///
+    /// * `view_point` - Where the scene is seen from.
+    pub fn sort_triangles_back_to_front(&mut self, view_point: &ViewPoint) {
+        let world_positions = &self.world_positions;
+        let mut sorted: Vec<(f64, [usize; 3])> = self
+            .triangles
+            .chunks_exact(3)
+            .map(|triangle| {
+                let center = (world_positions[triangle[0]]
+                    + world_positions[triangle[1]]
+                    + world_positions[triangle[2]])
+                    / 3.0;
+                (
+                    view_point.distance(center),
+                    [triangle[0], triangle[1], triangle[2]],
+                )
+            })
+            .collect();
+        sorted.sort_by(|(distance_a, _), (distance_b, _)| distance_b.total_cmp(distance_a));
+        self.triangles = sorted
+            .into_iter()
+            .flat_map(|(_, triangle)| triangle)
+            .collect();
+    }
     /// Uses the current w value to create the `clip_w_inv` values. Just does 1/w.
     ///
     /// This method is called when we enter clip space, as the 1/w at this point is linear in ndc
@@ -307,6 +464,61 @@ impl Geometry {
     pub fn uvs(&self) -> &[DVec2] {
         &self.uvs
     }
+    /// Mutable reference to the colors of the vertices making up the mesh.
+    pub fn colors_mut(&mut self) -> &mut [DVec4] {
+        &mut self.colors
+    }
+    /// Reference to the colors of the vertices making up the mesh.
+    pub fn colors(&self) -> &[DVec4] {
+        &self.colors
+    }
+    /// Mutable reference to the normals of the vertices making up the mesh.
+    pub fn normals_mut(&mut self) -> &mut [DVec3] {
+        &mut self.normals
+    }
+    /// Reference to the normals of the vertices making up the mesh.
+    pub fn normals(&self) -> &[DVec3] {
+        &self.normals
+    }
+    /// Reference to the tangents of the vertices making up the mesh.
+    pub fn tangents(&self) -> &[DVec4] {
+        &self.tangents
+    }
+    /// Reference to the world space position of the vertices making up the mesh. Empty if
+    /// [`Geometry::set_world_positions`] was never called.
+    pub fn world_positions(&self) -> &[DVec3] {
+        &self.world_positions
+    }
+    /// Reference to the lighting of the vertices making up the mesh. Empty if the lighting is not
+    /// evaluated per vertex.
+    pub fn vertex_shading(&self) -> &[Lighting] {
+        &self.vertex_shading
+    }
+    /// Reference to the light reaching the vertices making up the mesh from each light with a
+    /// shadow map, before the shadows are applied. The values of a vertex follow each other.
+    /// Empty if the lighting is not evaluated per vertex.
+    pub fn vertex_shadowed_shading(&self) -> &[Lighting] {
+        &self.vertex_shadowed_shading
+    }
+    /// Sets the lighting of each vertex making up the mesh.
+    ///
+    /// Both are interpolated along with the other vertex attributes during clipping.
+    ///
+    /// # Arguments
+    ///
+    /// * `vertex_shading` - The lighting of each vertex, as if nothing were in the shadow. Needs
+    ///   as many values as there are vertices.
+    /// * `vertex_shadowed_shading` - The light reaching each vertex from each light with a shadow
+    ///   map (see [`crate::pipeline::shader::Shader::shade_without_shadows`]). The values of a
+    ///   vertex follow each other, so it needs a multiple of the number of vertices.
+    pub fn set_vertex_shading(
+        &mut self,
+        vertex_shading: Vec<Lighting>,
+        vertex_shadowed_shading: Vec<Lighting>,
+    ) {
+        self.vertex_shading = vertex_shading;
+        self.vertex_shadowed_shading = vertex_shadowed_shading;
+    }
     /// Mutable reference to the triangles making up the mesh.
     pub fn triangles_mut(&mut self) -> &mut [usize] {
         &mut self.triangles
@@ -327,9 +539,9 @@ impl Geometry {
     pub fn clip_w_inv(&self) -> &[f64] {
         &self.clip_w_inv
     }
-    /// Gets the texture id if there is one.
-    pub fn texture_id(&self) -> Option<u32> {
-        self.texture_id
+    /// Gets the material id if there is one.
+    pub fn material_id(&self) -> Option<u32> {
+        self.material_id
     }
     /// The normals for the triangles if they were in world space.
     pub fn triangle_normals(&self) -> &[DVec3] {
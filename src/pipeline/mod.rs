@@ -1,20 +1,42 @@
 //! Contains everytihng that will be needed to render the scene.
 
 use geometry::Geometry;
+use glam::{DMat4, DVec2, DVec3, U8Vec3, Vec4Swizzles};
+use post_process::PostEffect;
 use rasterizer::Rasterizer;
-use shader::Shader;
+use shader::{FrameFog, FrameLight, Shader, ShaderType, ViewPoint};
+use shadow::{ShadowFace, ShadowMap, CUBE_FACES};
 
-use crate::{graphics::screen::Screen, scene::Scene};
+use crate::{
+    graphics::{color, render_target::RenderTarget},
+    resources::{material::Material, mesh::Mesh},
+    scene::{
+        camera::Projection,
+        light::{Light, LightType},
+        Scene,
+    },
+};
 
 pub mod geometry;
+pub mod post_process;
 mod rasterizer;
 mod transforms;
 pub mod shader;
+pub mod shadow;
 
 /// Contains values imprtant for rendering.
 pub struct Pipeline {
     rasterizer: rasterizer::Rasterizer,
     shader: Shader,
+    /// Renders the depth of the scene as seen from the lights casting shadows. Only created once
+    /// a light needs it, with the size of its shadow map.
+    shadow_rasterizer: Option<Rasterizer>,
+    /// Effects applied to the whole frame once the scene is rasterized, in order.
+    post_effects: Vec<Box<dyn PostEffect>>,
+    /// Holds the frame while a post effect is applied.
+    post_scratch: Vec<u8>,
+    /// Color (RGB) the screen was last cleared with.
+    clear_color: [u8; 3],
 }
 
 impl Pipeline {
@@ -26,76 +48,709 @@ impl Pipeline {
     /// * `width` - Width of the screen the pipeline will draw on.
     /// * `height` - Height of the screen the pipeline will draw on.
     /// * `shader` - What type of shader to use in the pipeline.
-    pub fn new(tile_size: usize, width: usize, height: usize, shader: Shader) -> Self {
+    /// * `msaa` - Number of samples per pixel used to smooth the edges of the triangles.
+    pub fn new(tile_size: usize, width: usize, height: usize, shader: Shader, msaa: Msaa) -> Self {
         Pipeline {
-            rasterizer: Rasterizer::new(tile_size, width, height),
+            rasterizer: Rasterizer::new(tile_size, width, height, msaa),
             shader: shader,
+            shadow_rasterizer: None,
+            post_effects: Vec::new(),
+            post_scratch: Vec::new(),
+            clear_color: [0; 3],
         }
     }
+    /// Sets whether the scene is rendered in high dynamic range (HDR).
+    ///
+    /// In HDR, lights are not clamped when added up, and the colors are tone mapped when the scene
+    /// is written to the render target (before the post effects). This takes 4 times as much
+    /// memory for the colors.
+    ///
+    /// # Arguments
+    ///
+    /// * `hdr` - Exposure and tone mapping of the scene. None renders with 8 bits per channel.
+    pub fn set_hdr(&mut self, hdr: Option<Hdr>) {
+        self.rasterizer.set_hdr(hdr);
+    }
+    /// Sets whether the scene is lit in linear space and sRGB encoded when written to the render
+    /// target. Enabled by default.
+    ///
+    /// Light adds up linearly, so lighting and blending are only correct in linear space, while
+    /// screens and images expect sRGB colors. sRGB textures (see
+    /// [`crate::resources::texture::Format`]) are decoded to linear whether this is enabled or
+    /// not. When disabled, the linear colors are written as they are, which darkens the
+    /// midtones.
+    ///
+    /// # Arguments
+    ///
+    /// * `srgb_output` - Whether the colors are sRGB encoded.
+    pub fn set_srgb_output(&mut self, srgb_output: bool) {
+        self.rasterizer.set_srgb_output(srgb_output);
+    }
+    /// Adds an effect applied to the whole frame once the scene is rasterized.
+    ///
+    /// Effects are applied in the order they are added. See [`post_process`] for the available
+    /// effects.
+    ///
+    /// # Arguments
+    ///
+    /// * `effect` - The effect to add.
+    pub fn add_post_effect(&mut self, effect: impl PostEffect + 'static) {
+        self.post_effects.push(Box::new(effect));
+    }
     /// Clear rasterizer and others values before processing the scene again.
     pub fn clear(&mut self, color: &[u8]) {
         self.rasterizer.clear_with_color(color);
+        self.clear_color = [color[0], color[1], color[2]];
     }
     /// Processes the data contained within the scene and prepares it for rendering.
     ///
     /// # Arguments
     ///
     /// * `scene` - The scene that will be processed. Every mesh withing will be rendererd.
-    /// * `screen` - Where the scene will be rasterized.
-    pub fn process_scene(&mut self, scene: &Scene, screen: &mut Screen) {
-        let textures = scene.texture_catalog().textures();
+    /// * `target` - Where the scene will be rasterized. Needs the same dimensions as the pipeline.
+    ///
+    /// Rendering to a [`crate::graphics::frame_buffer::FrameBuffer`] instead of a
+    /// [`crate::graphics::screen::Screen`] does not require a window.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target's dimensions differ from the pipeline's.
+    pub fn process_scene(&mut self, scene: &Scene, target: &mut dyn RenderTarget) {
+        assert!(
+            target.width() == self.rasterizer.width()
+                && target.height() == self.rasterizer.height(),
+            "Render target ({}x{}) does not match the pipeline ({}x{}).",
+            target.width(),
+            target.height(),
+            self.rasterizer.width(),
+            self.rasterizer.height()
+        );
+        let textures = scene.texture_catalog();
+        let materials = scene.material_catalog();
+        // Used by the meshes without a material.
+        let default_material = Material::new();
         let camera = scene.camera();
         let projection = camera.projection();
         let camera_inv_transform = camera.transform().inverse();
-        // Handle the scene differently ddepending on projection method.
-        match projection {
-            crate::scene::camera::Projection::Perspective {
+        // Get the camera projection transform.
+        let projection_transform = match projection {
+            Projection::Perspective {
                 near_clip,
                 far_clip,
                 aspect_ratio,
                 hfov,
-            } => {
-                // Get the camera perspective transform
-                let perspective_transform =
-                    transforms::perspective_transform(*near_clip, *far_clip, *aspect_ratio, *hfov);
-                // Process all the meshes in order to rasterize them.
-                for mesh in scene.meshes() {
-                    let mut geometry = Geometry::from_mesh(mesh);
-                    // Convert geometry to world coordinates.
-                    geometry.lin_transform(mesh.transform());
-                    // Do backface culling.
-                    geometry.cull_backface(&camera.position());
-                    // Convert geometry to view space.
-                    geometry.lin_transform(&camera_inv_transform);
-                    // Convert to clip space.
-                    geometry.lin_transform(&perspective_transform);
-                    // Clip trianlges to view frustum.
-                    geometry.clip_geometry();
-                    // Set important values for rasterization.
-                    geometry.set_clip_w_inv();
-                    // let clip_to_world = (perspective_transform*camera_inv_transform).inverse();
-                    let clip_to_world = (perspective_transform*camera_inv_transform).inverse();
-                    geometry.set_triangle_world_normals(clip_to_world);
-                    // Convert to ndc space.
-                    geometry.perspective_divide();
-                    // Convert to screen space.
-                    geometry.lin_transform(&transforms::ndc_to_screen_transform(
-                        screen.width(),
-                        screen.height(),
-                    ));
-                    // Rasterize to screen.
-                    // First, get the geometry's texture.
-                    let texture = if let Some(id) = geometry.texture_id() {
-                        textures.get(&id)
-                    } else {
-                        None
-                    };
-                    self.rasterizer.rasterize_threaded(&geometry, screen, texture, &self.shader, scene.lights());
+            } => transforms::perspective_transform(*near_clip, *far_clip, *aspect_ratio, *hfov),
+            Projection::Orthographic {
+                near_clip,
+                far_clip,
+                width,
+                height,
+            } => transforms::orthographic_transform(*near_clip, *far_clip, *width, *height),
+        };
+        let clip_to_world = (projection_transform * camera_inv_transform).inverse();
+        // Move the lights attached to the camera in world space, and find what they cannot reach.
+        let lights: Vec<FrameLight> = scene
+            .lights()
+            .iter()
+            .map(|light| {
+                let light = light.in_world_space(camera.transform());
+                let shadow_map = if light.casts_shadows {
+                    self.render_shadow_map(scene, &light, &clip_to_world)
+                } else {
+                    None
+                };
+                FrameLight { light, shadow_map }
+            })
+            .collect();
+        // Fog without a color blends in with the background.
+        let fog = scene.fog().map(|fog| {
+            let clear_color = U8Vec3::from_array(self.clear_color);
+            let background = if self.rasterizer.srgb_output() {
+                color::decode_srgb_color(clear_color.extend(255)).xyz()
+            } else {
+                clear_color.as_dvec3() / 255.0
+            };
+            FrameFog {
+                fog: *fog,
+                color: fog.color.unwrap_or(background),
+                camera_position: *camera.position(),
+            }
+        });
+        self.rasterizer.set_fog(fog);
+        // Reflective materials reflect the skybox, unless the scene has its own environment map.
+        let environment = scene.environment_map().or(scene.skybox());
+        self.rasterizer.set_environment(environment.copied());
+        // Where the scene is seen from, needed for specular highlights.
+        let view_point = match projection {
+            Projection::Perspective { .. } => ViewPoint::Position(*camera.position()),
+            Projection::Orthographic { .. } => ViewPoint::Direction(camera.camera_orientation()),
+        };
+        // Opaque meshes are drawn first, then the transparent ones from back to front so they
+        // blend over everything behind them.
+        let (opaque_meshes, mut transparent_meshes): (Vec<_>, Vec<_>) = scene
+            .meshes()
+            .iter()
+            .map(|mesh| {
+                let material = mesh
+                    .material_id()
+                    .and_then(|id| materials.material_from_id(id))
+                    .unwrap_or(&default_material);
+                (mesh, material)
+            })
+            .partition(|(_, material)| !material.blend_mode.is_transparent());
+        let mesh_distance = |mesh: &Mesh| view_point.distance(mesh.transform().w_axis.truncate());
+        transparent_meshes.sort_by(|(mesh_a, _), (mesh_b, _)| {
+            mesh_distance(mesh_b).total_cmp(&mesh_distance(mesh_a))
+        });
+        // Process a mesh in order to rasterize it.
+        let rasterize_mesh = |rasterizer: &mut Rasterizer, mesh: &Mesh, material: &Material| {
+            let mut geometry = Geometry::from_mesh(mesh);
+            // Convert geometry to world coordinates.
+            geometry.lin_transform(mesh.transform());
+            geometry.transform_normals(mesh.transform());
+            geometry.set_world_positions();
+            // Do backface culling. This is handled differently depending on projection method.
+            // Both sides of double sided materials are drawn.
+            if !material.double_sided {
+                match projection {
+                    Projection::Perspective { .. } => geometry.cull_backface(camera.position()),
+                    Projection::Orthographic { .. } => {
+                        geometry.cull_backface_orthographic(&camera.camera_orientation())
+                    }
                 }
             }
-            crate::scene::camera::Projection::Orthographic { .. } => {
-                todo!("Implement orthographic projection.");
+            // Evaluate the lighting at the vertices when interpolating it across the triangles.
+            if let ShaderType::Gouraud = self.shader.shader_type {
+                // The shadows are applied at every pixel.
+                let mut shadowed_shading = Vec::new();
+                let vertex_shading = geometry
+                    .normals()
+                    .iter()
+                    .zip(geometry.world_positions())
+                    .map(|(&normal, &position)| {
+                        // Light the side of double sided materials that faces the viewer.
+                        let to_eye = view_point.direction_to_eye(position);
+                        let normal = if material.double_sided && normal.dot(to_eye) < 0.0 {
+                            -normal
+                        } else {
+                            normal
+                        };
+                        self.shader.shade_without_shadows(
+                            normal,
+                            position,
+                            &view_point,
+                            material,
+                            &lights,
+                            &mut shadowed_shading,
+                        )
+                    })
+                    .collect();
+                geometry.set_vertex_shading(vertex_shading, shadowed_shading);
+            }
+            // Convert geometry to view space.
+            geometry.lin_transform(&camera_inv_transform);
+            // Convert to clip space.
+            geometry.lin_transform(&projection_transform);
+            // Clip trianlges to view frustum.
+            geometry.clip_geometry();
+            if material.blend_mode.is_transparent() {
+                geometry.sort_triangles_back_to_front(&view_point);
+            }
+            // Set important values for rasterization.
+            geometry.set_clip_w_inv();
+            geometry.set_triangle_world_normals(clip_to_world);
+            // Convert to ndc space.
+            geometry.perspective_divide();
+            // Convert to screen space.
+            geometry.lin_transform(&transforms::ndc_to_screen_transform(
+                target.width(),
+                target.height(),
+            ));
+            // Rasterize to screen.
+            rasterizer.rasterize_threaded(
+                &geometry,
+                material,
+                textures,
+                &self.shader,
+                &view_point,
+                &lights,
+            );
+        };
+        for (mesh, material) in opaque_meshes {
+            rasterize_mesh(&mut self.rasterizer, mesh, material);
+        }
+        // The sky fills what the opaque meshes left empty, behind the transparent meshes.
+        if let Some(skybox) = scene.skybox() {
+            let screen_to_ndc =
+                transforms::ndc_to_screen_transform(target.width(), target.height()).inverse();
+            self.rasterizer
+                .draw_skybox(skybox, textures, &(clip_to_world * screen_to_ndc));
+        }
+        for (mesh, material) in transparent_meshes {
+            rasterize_mesh(&mut self.rasterizer, mesh, material);
+        }
+        // Write the rasterized scene to the target.
+        self.rasterizer.write_to_target(target);
+        post_process::apply_effects(&self.post_effects, target, &mut self.post_scratch);
+    }
+    /// Renders the depth of the scene as seen from a light.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene whose meshes cast the shadows.
+    /// * `light` - The light, in world space.
+    /// * `clip_to_world` - Transform from the clip space of the camera to world space.
+    ///
+    /// # Return
+    ///
+    /// The shadow map of the light. None if the light is of type `Spot` or if nothing can be
+    /// shadowed.
+    fn render_shadow_map(
+        &mut self,
+        scene: &Scene,
+        light: &Light,
+        clip_to_world: &DMat4,
+    ) -> Option<ShadowMap> {
+        let size = light.shadow_map_size.max(1);
+        match light.light_type {
+            LightType::AtInfinity(direction) => {
+                self.render_directional_shadow_map(scene, direction, size, clip_to_world)
+            }
+            LightType::Point { position, .. } => self.render_cube_shadow_map(scene, position, size),
+            LightType::Spot { .. } => None,
+        }
+    }
+    /// Renders the depth of the scene as seen from a directional light.
+    ///
+    /// The light looks at the scene through an orthographic projection fitted around the part of
+    /// the scene visible from the camera, so that the pixels of the map are not wasted on what
+    /// is off screen. Meshes between the light and the visible part of the scene are still
+    /// rendered, since their shadows may fall on screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene whose meshes cast the shadows.
+    /// * `direction` - Direction of the light (normalized).
+    /// * `size` - Number of pixels along each side of the map.
+    /// * `clip_to_world` - Transform from the clip space of the camera to world space.
+    ///
+    /// # Return
+    ///
+    /// The shadow map of the light. None if nothing can be shadowed.
+    fn render_directional_shadow_map(
+        &mut self,
+        scene: &Scene,
+        direction: DVec3,
+        size: usize,
+        clip_to_world: &DMat4,
+    ) -> Option<ShadowMap> {
+        // Light space, where the light looks towards -Z.
+        let up = if direction.y.abs() > 0.99 {
+            DVec3::X
+        } else {
+            DVec3::Y
+        };
+        let light_view = DMat4::look_to_rh(DVec3::ZERO, direction, up);
+        // Bounds (in light space) of the view frustum, and of the meshes.
+        let frustum_corners = (0..8).map(|i| {
+            let corner = DVec3::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 },
+            );
+            light_view.transform_point3(clip_to_world.project_point3(corner))
+        });
+        let (frustum_min, frustum_max) = bounds(frustum_corners)?;
+        let mut scene_bounds = None;
+        let mut casters_bounds = None;
+        for mesh in scene.meshes() {
+            let Some(mesh_bounds) = mesh_bounds(mesh, &light_view) else {
+                continue;
+            };
+            scene_bounds = merge_bounds(scene_bounds, mesh_bounds);
+            if mesh.casts_shadows() {
+                casters_bounds = merge_bounds(casters_bounds, mesh_bounds);
+            }
+        }
+        let (scene_min, scene_max) = scene_bounds?;
+        let (_, casters_max) = casters_bounds?;
+        // Only the visible part of the scene receives shadows.
+        let min = frustum_min.max(scene_min);
+        let max = frustum_max.min(scene_max);
+        if min.x >= max.x || min.y >= max.y || min.z >= max.z {
+            return None;
+        }
+        // Place the light in front of every caster, with some margin around the depth range.
+        let margin = 1.0;
+        let light_z = casters_max.z.max(max.z) + margin;
+        let view = DMat4::from_translation(-DVec3::new(
+            (min.x + max.x) / 2.0,
+            (min.y + max.y) / 2.0,
+            light_z,
+        )) * light_view;
+        let (width, height) = (max.x - min.x, max.y - min.y);
+        let projection = transforms::orthographic_transform(
+            margin as f32,
+            (light_z - min.z + margin) as f32,
+            width as f32,
+            height as f32,
+        );
+        Some(ShadowMap::Directional {
+            face: self.render_shadow_face(scene, projection * view, size),
+            texel_size: width.max(height) / size as f64,
+        })
+    }
+    /// Renders the depth of the scene as seen from a point light, through the six faces of a
+    /// cube centered on the light.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene whose meshes cast the shadows.
+    /// * `position` - Position (in world space) of the light.
+    /// * `size` - Number of pixels along each side of the faces.
+    ///
+    /// # Return
+    ///
+    /// The shadow map of the light. None if nothing can be shadowed.
+    fn render_cube_shadow_map(
+        &mut self,
+        scene: &Scene,
+        position: DVec3,
+        size: usize,
+    ) -> Option<ShadowMap> {
+        // The far plane has to reach every caster.
+        let light_view = DMat4::from_translation(-position);
+        let casters_distance = scene
+            .meshes()
+            .iter()
+            .filter(|mesh| mesh.casts_shadows())
+            .filter_map(|mesh| mesh_bounds(mesh, &light_view))
+            .map(|(min, max)| min.abs().max(max.abs()).length())
+            .reduce(f64::max)?;
+        let far_clip = casters_distance + 1.0;
+        let projection = transforms::perspective_transform(
+            (far_clip / 1000.0) as f32,
+            far_clip as f32,
+            1.0,
+            90.0,
+        );
+        let faces = CUBE_FACES.map(|(direction, up)| {
+            let view = DMat4::look_to_rh(position, direction, up);
+            self.render_shadow_face(scene, projection * view, size)
+        });
+        Some(ShadowMap::Cube {
+            position,
+            faces: Box::new(faces),
+            // A 90° face is 2 units wide at a distance of 1 from the light.
+            texel_size: 2.0 / size as f64,
+        })
+    }
+    /// Renders the depth of the meshes casting shadows through a single projection.
+    ///
+    /// # Arguments
+    ///
+    /// * `scene` - The scene whose meshes cast the shadows.
+    /// * `world_to_clip` - Transform from world space to the clip space of the projection.
+    /// * `size` - Number of pixels along each side of the face.
+    fn render_shadow_face(
+        &mut self,
+        scene: &Scene,
+        world_to_clip: DMat4,
+        size: usize,
+    ) -> ShadowFace {
+        // Lights can each have a different resolution.
+        let shadow_rasterizer = match &mut self.shadow_rasterizer {
+            Some(shadow_rasterizer) if shadow_rasterizer.width() == size => {
+                shadow_rasterizer.clear();
+                shadow_rasterizer
             }
+            shadow_rasterizer => shadow_rasterizer.insert(Rasterizer::new_depth_only(
+                self.rasterizer.tile_size(),
+                size,
+                size,
+            )),
+        };
+        let ndc_to_map = transforms::ndc_to_screen_transform(size, size);
+        for mesh in scene.meshes().iter().filter(|mesh| mesh.casts_shadows()) {
+            let mut geometry = Geometry::from_mesh(mesh);
+            geometry.lin_transform(&(world_to_clip * *mesh.transform()));
+            geometry.clip_geometry();
+            geometry.perspective_divide();
+            geometry.lin_transform(&ndc_to_map);
+            shadow_rasterizer.rasterize_depth_threaded(&geometry);
+        }
+        ShadowFace::new(
+            ndc_to_map * world_to_clip,
+            size,
+            shadow_rasterizer.depth_buffer(),
+        )
+    }
+}
+// Getters and setters.
+impl Pipeline {
+    /// Obtains the exposure and tone mapping of the scene, if it is rendered in HDR.
+    pub fn hdr(&self) -> Option<Hdr> {
+        self.rasterizer.hdr()
+    }
+    /// Whether the colors are sRGB encoded when written to the render target.
+    pub fn srgb_output(&self) -> bool {
+        self.rasterizer.srgb_output()
+    }
+    /// Obtains a mutable reference to the post effects, to reorder or remove them.
+    pub fn post_effects_mut(&mut self) -> &mut Vec<Box<dyn PostEffect>> {
+        &mut self.post_effects
+    }
+}
+/// Multisample anti-aliasing: how many samples per pixel are tested for coverage and depth.
+///
+/// The edges of the triangles get smoother with more samples, but every sample takes memory
+/// and needs to be tested. Pixels are still only shaded once.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Msaa {
+    /// A single sample at the center of the pixels.
+    Off,
+    /// 2 samples per pixel.
+    X2,
+    /// 4 samples per pixel.
+    X4,
+    /// 8 samples per pixel.
+    X8,
+}
+impl Msaa {
+    /// Obtains the number of samples per pixel.
+    pub fn nb_samples(&self) -> usize {
+        self.sample_offsets().len()
+    }
+    /// Obtains the positions of the samples relative to the center of the pixels, in pixels.
+    ///
+    /// Uses the standard (rotated grid) sample patterns, so that nearly horizontal and vertical
+    /// edges are also smoothed.
+    pub fn sample_offsets(&self) -> &'static [DVec2] {
+        const OFF: [DVec2; 1] = [DVec2::ZERO];
+        const X2: [DVec2; 2] = [DVec2::new(0.25, 0.25), DVec2::new(-0.25, -0.25)];
+        const X4: [DVec2; 4] = [
+            DVec2::new(-0.125, -0.375),
+            DVec2::new(0.375, -0.125),
+            DVec2::new(-0.375, 0.125),
+            DVec2::new(0.125, 0.375),
+        ];
+        const X8: [DVec2; 8] = [
+            DVec2::new(0.0625, -0.1875),
+            DVec2::new(-0.0625, 0.1875),
+            DVec2::new(0.3125, 0.0625),
+            DVec2::new(-0.1875, -0.3125),
+            DVec2::new(-0.3125, 0.3125),
+            DVec2::new(-0.4375, -0.0625),
+            DVec2::new(0.1875, 0.4375),
+            DVec2::new(0.4375, -0.4375),
+        ];
+        match self {
+            Msaa::Off => &OFF,
+            Msaa::X2 => &X2,
+            Msaa::X4 => &X4,
+            Msaa::X8 => &X8,
         }
     }
 }
+/// High dynamic range rendering: colors are accumulated in floating point without being clamped,
+/// and brought back between 0-1 once the scene is rasterized.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hdr {
+    /// Multiplies the colors before they are tone mapped. Bigger values brighten the image.
+    pub exposure: f64,
+    /// How the colors are brought back between 0-1.
+    pub tone_mapping: ToneMapping,
+}
+impl Hdr {
+    /// Creates an HDR setting with an exposure of 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `tone_mapping` - How the colors are brought back between 0-1.
+    pub fn new(tone_mapping: ToneMapping) -> Self {
+        Hdr {
+            exposure: 1.0,
+            tone_mapping,
+        }
+    }
+    /// Applies the exposure to a color, and tone maps it.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - The linear color (RGB). Each channel is a value of 0 or more.
+    ///
+    /// # Return
+    ///
+    /// The tone mapped color, with each channel between 0-1.
+    pub fn tone_map(&self, color: DVec3) -> DVec3 {
+        self.tone_mapping
+            .apply(color.max(DVec3::ZERO) * self.exposure)
+    }
+}
+/// Operators bringing colors of any brightness back between 0-1.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ToneMapping {
+    /// Clamps every channel to 1. Anything brighter is lost.
+    Clamp,
+    /// `color / (1 + color)`. Never saturates, but dulls the bright colors.
+    Reinhard,
+    /// Fit of the filmic curve of the Academy Color Encoding System (ACES), by Krzysztof
+    /// Narkowicz. Adds contrast, and saturates smoothly.
+    Aces,
+}
+impl ToneMapping {
+    /// Tone maps a color.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - The linear color (RGB). Each channel is a value of 0 or more.
+    ///
+    /// # Return
+    ///
+    /// The tone mapped color, with each channel between 0-1.
+    pub fn apply(&self, color: DVec3) -> DVec3 {
+        let mapped = match self {
+            ToneMapping::Clamp => color,
+            ToneMapping::Reinhard => color / (color + 1.0),
+            ToneMapping::Aces => {
+                (color * (color * 2.51 + 0.03)) / (color * (color * 2.43 + 0.59) + 0.14)
+            }
+        };
+        mapped.clamp(DVec3::ZERO, DVec3::ONE)
+    }
+}
+/// Obtains the axis aligned box containing a mesh.
+///
+/// # Arguments
+///
+/// * `mesh` - The mesh.
+/// * `world_to_space` - Transform from world space to the space the box is aligned with.
+///
+/// # Return
+///
+/// The minimum and maximum corners of the box. None if the mesh has no vertices.
+fn mesh_bounds(mesh: &Mesh, world_to_space: &DMat4) -> Option<(DVec3, DVec3)> {
+    let mesh_to_space = *world_to_space * *mesh.transform();
+    bounds(
+        mesh.vertices()
+            .iter()
+            .map(|vertex| mesh_to_space.transform_point3(vertex.position().xyz())),
+    )
+}
+/// Obtains the axis aligned box containing a set of points.
+///
+/// # Return
+///
+/// The minimum and maximum corners of the box. None if there are no points.
+fn bounds(points: impl Iterator<Item = DVec3>) -> Option<(DVec3, DVec3)> {
+    points.fold(None, |bounds, point| merge_bounds(bounds, (point, point)))
+}
+/// Obtains the axis aligned box containing two other boxes.
+///
+/// # Arguments
+///
+/// * `bounds` - The minimum and maximum corners of the first box, if there is one.
+/// * `other` - The minimum and maximum corners of the second box.
+fn merge_bounds(bounds: Option<(DVec3, DVec3)>, other: (DVec3, DVec3)) -> Option<(DVec3, DVec3)> {
+    match bounds {
+        Some((min, max)) => Some((min.min(other.0), max.max(other.1))),
+        None => Some(other),
+    }
+}
+#[cfg(test)]
+mod tests {
+    use glam::DQuat;
+
+    use super::*;
+    use crate::{
+        graphics::frame_buffer::FrameBuffer,
+        resources::{
+            loaders::{DefaultMesh, MeshLoader},
+            texture::{Format, Texture},
+        },
+        scene::camera::{Camera, CameraStyle},
+    };
+
+    const WIDTH: usize = 32;
+    const HEIGHT: usize = 24;
+
+    /// Creates a scene with a cube in front of the camera, covering the center of the screen.
+    /// The cube is textured with a single texel.
+    fn cube_scene(texel: [u8; 3], format: Format) -> Scene {
+        let camera = Camera::new_perspective(
+            &DVec3::ZERO,
+            &DQuat::IDENTITY,
+            0.1,
+            100.0,
+            WIDTH as f32 / HEIGHT as f32,
+            90.0,
+            CameraStyle::FPSLike,
+        );
+        let mut scene = Scene::with_camera(camera);
+        let texture = Texture::from_pixels(1, 1, &texel.to_vec(), format).unwrap();
+        let texture_id = scene
+            .texture_catalog_mut()
+            .add_texture("texel".to_string(), texture)
+            .unwrap();
+        let material_id = scene
+            .material_catalog_mut()
+            .add_material("texel".to_string(), Material::from_texture(texture_id))
+            .unwrap();
+        let mut cube = MeshLoader::new().load_default_mesh(
+            DefaultMesh::Cube {
+                size: 2.0,
+                u_repeat: 1.0,
+                v_repeat: 1.0,
+            },
+            Some(material_id),
+        );
+        cube.translate(DVec3::new(0.0, 0.0, -3.0));
+        scene.add_mesh(cube);
+        scene
+    }
+
+    /// Obtains the RGBA color of the pixel at the center of the frame buffer.
+    fn center_color(frame_buffer: &FrameBuffer) -> &[u8] {
+        let index = 4 * (HEIGHT / 2 * WIDTH + WIDTH / 2);
+        &frame_buffer.color()[index..index + 4]
+    }
+
+    #[test]
+    fn srgb_texel_is_unchanged_by_default() {
+        let scene = cube_scene([128; 3], Format::SRGB24);
+        // Full ambient light and no lights, so the texel is neither darkened nor brightened.
+        let shader = Shader::new(DVec3::ONE, ShaderType::Flat);
+        let mut pipeline = Pipeline::new(16, WIDTH, HEIGHT, shader, Msaa::Off);
+        let mut frame_buffer = FrameBuffer::new(WIDTH, HEIGHT);
+        pipeline.clear(frame_buffer.bg_color());
+        pipeline.process_scene(&scene, &mut frame_buffer);
+        assert_eq!(center_color(&frame_buffer), [128, 128, 128, 255]);
+    }
+
+    #[test]
+    fn lit_cube_is_rendered() {
+        let mut scene = cube_scene([255; 3], Format::SRGB24);
+        scene.add_light(Light::new(
+            1.0,
+            U8Vec3::splat(255),
+            LightType::AtInfinity(DVec3::NEG_Z),
+        ));
+        let shader = Shader::new(DVec3::splat(0.1), ShaderType::Phong);
+        let mut pipeline = Pipeline::new(16, WIDTH, HEIGHT, shader, Msaa::Off);
+        let mut frame_buffer = FrameBuffer::new(WIDTH, HEIGHT);
+        pipeline.clear(frame_buffer.bg_color());
+        pipeline.process_scene(&scene, &mut frame_buffer);
+        assert_ne!(center_color(&frame_buffer), frame_buffer.bg_color());
+        assert!(frame_buffer.depth()[HEIGHT / 2 * WIDTH + WIDTH / 2].is_finite());
+    }
+
+    #[test]
+    #[should_panic(expected = "does not match the pipeline")]
+    fn mismatched_render_target_panics() {
+        let scene = cube_scene([255; 3], Format::SRGB24);
+        let shader = Shader::new(DVec3::ONE, ShaderType::Flat);
+        let mut pipeline = Pipeline::new(16, WIDTH, HEIGHT, shader, Msaa::Off);
+        let mut frame_buffer = FrameBuffer::new(WIDTH + 1, HEIGHT);
+        pipeline.process_scene(&scene, &mut frame_buffer);
+    }
+}
@@ -0,0 +1,373 @@
+//! Contains the effects applied to the whole frame once the scene is rasterized.
+
+use glam::{DVec2, DVec3, DVec4, U8Vec4, Vec4Swizzles};
+use rayon::{iter::IndexedParallelIterator, iter::ParallelIterator, slice::ParallelSliceMut};
+
+use crate::graphics::render_target::RenderTarget;
+
+/// Weights of the color channels (RGB) giving the perceived brightness (luma) of a color.
+const LUMA_WEIGHTS: DVec3 = DVec3::new(0.299, 0.587, 0.114);
+/// Number of pixels FXAA walks along an edge, in each direction, to find its ends.
+const FXAA_SEARCH_STEPS: usize = 12;
+
+/// An effect applied to the whole frame once the scene is rasterized.
+///
+/// Implement this trait to add your own effects to the [`super::Pipeline`]. The effect is
+/// computed for every pixel independently (and in parallel), while reading from the frame as it
+/// was before the effect.
+/// ```ignore
+/// struct Invert;
+/// impl PostEffect for Invert {
+///     fn apply(&self, frame: &Frame, x: usize, y: usize) -> DVec4 {
+///         let color = frame.color(x as i64, y as i64);
+///         (DVec3::ONE - color.xyz()).extend(color.w)
+///     }
+/// }
+/// pipeline.add_post_effect(Invert);
+/// ```
+pub trait PostEffect: Send + Sync {
+    /// Computes the color of a pixel once the effect is applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - The frame before the effect is applied.
+    /// * `x` - Horizontal position of the pixel (0 is the left of the frame).
+    /// * `y` - Vertical position of the pixel (0 is the top of the frame).
+    ///
+    /// # Return
+    ///
+    /// The color (RGBA) of the pixel, with each channel between 0-1.
+    fn apply(&self, frame: &Frame, x: usize, y: usize) -> DVec4;
+}
+/// Applies post effects to a render target, one after the other.
+///
+/// Each effect is applied to the whole frame before the next one starts, with the rows of the
+/// frame split between threads.
+///
+/// # Arguments
+///
+/// * `effects` - The effects, in the order they are applied.
+/// * `target` - The render target holding the frame. Nothing is done if its color buffer is not
+///   available.
+/// * `scratch` - Holds the frame while an effect is applied, before it is copied back to the
+///   target. Resized as needed.
+pub fn apply_effects(
+    effects: &[Box<dyn PostEffect>],
+    target: &mut dyn RenderTarget,
+    scratch: &mut Vec<u8>,
+) {
+    let (width, height) = (target.width(), target.height());
+    if width == 0 || height == 0 {
+        return;
+    }
+    for effect in effects {
+        let Some(color) = target.color_buffer() else {
+            return;
+        };
+        let frame = Frame {
+            width,
+            height,
+            color,
+            depth: target.depth_buffer(),
+        };
+        scratch.resize(color.len(), 0);
+        scratch
+            .par_chunks_exact_mut(width * 4)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                    let color = effect.apply(&frame, x, y).clamp(DVec4::ZERO, DVec4::ONE);
+                    pixel.copy_from_slice(&(color * 255.0).round().as_u8vec4().to_array());
+                }
+            });
+        if let Some(color) = target.color_buffer_mut() {
+            color.copy_from_slice(scratch);
+        }
+    }
+}
+/// A rendered frame, as given to the post effects.
+pub struct Frame<'a> {
+    /// Width (in pixels) of the frame.
+    width: usize,
+    /// Height (in pixels) of the frame.
+    height: usize,
+    /// The RGBA pixel values. Left to right, top to bottom.
+    color: &'a [u8],
+    /// The depth value of every pixel, if the render target keeps them.
+    depth: Option<&'a [f64]>,
+}
+impl Frame<'_> {
+    /// Obtains the color of a pixel.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Horizontal position of the pixel. Clamped to the edges of the frame.
+    /// * `y` - Vertical position of the pixel. Clamped to the edges of the frame.
+    ///
+    /// # Return
+    ///
+    /// The color (RGBA) of the pixel, with each channel between 0-1.
+    pub fn color(&self, x: i64, y: i64) -> DVec4 {
+        let index = 4 * self.index(x, y);
+        U8Vec4::from_slice(&self.color[index..index + 4]).as_dvec4() / 255.0
+    }
+    /// Obtains the color of the frame between pixels by blending the 4 closest ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position (in pixels) in the frame. The center of the top left pixel is at
+    ///   (0.5, 0.5).
+    ///
+    /// # Return
+    ///
+    /// The color (RGBA), with each channel between 0-1.
+    pub fn sample(&self, position: DVec2) -> DVec4 {
+        let position = position - 0.5;
+        let top_left = position.floor();
+        let t = position - top_left;
+        let (x, y) = (top_left.x as i64, top_left.y as i64);
+        let top = self.color(x, y).lerp(self.color(x + 1, y), t.x);
+        let bottom = self.color(x, y + 1).lerp(self.color(x + 1, y + 1), t.x);
+        top.lerp(bottom, t.y)
+    }
+    /// Obtains the depth of a pixel. Smaller values are closer to the camera.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - Horizontal position of the pixel. Clamped to the edges of the frame.
+    /// * `y` - Vertical position of the pixel. Clamped to the edges of the frame.
+    ///
+    /// # Return
+    ///
+    /// The depth of the pixel. None if the render target does not keep depth values.
+    pub fn depth(&self, x: i64, y: i64) -> Option<f64> {
+        self.depth.map(|depth| depth[self.index(x, y)])
+    }
+    /// Obtains the index of a pixel in the buffers, after clamping its position to the frame.
+    fn index(&self, x: i64, y: i64) -> usize {
+        let x = x.clamp(0, self.width as i64 - 1) as usize;
+        let y = y.clamp(0, self.height as i64 - 1) as usize;
+        x + y * self.width
+    }
+}
+// Getters and setters.
+impl Frame<'_> {
+    /// Width (in pixels) of the frame.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    /// Height (in pixels) of the frame.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+/// Obtains the perceived brightness of a color.
+fn luma(color: DVec4) -> f64 {
+    color.xyz().dot(LUMA_WEIGHTS)
+}
+////////////////////////////////////////////////////////////
+/// Fast approximate anti-aliasing.
+///
+/// Finds the edges from the contrast between neighboring pixels, and blends the pixels along
+/// them. Smooths every edge (including the ones inside textures) for a fixed cost, but blurs the
+/// image slightly.
+pub struct Fxaa {
+    /// Minimum contrast, relative to the brightest neighboring pixel, needed to detect an edge.
+    /// Smaller values smooth more edges.
+    pub edge_threshold: f64,
+    /// Minimum contrast needed to detect an edge in dark areas.
+    pub edge_threshold_min: f64,
+    /// How much details the size of a pixel are smoothed. A value between 0-1, where 0 keeps
+    /// them sharp.
+    pub subpixel: f64,
+}
+impl Fxaa {
+    /// Creates an FXAA effect with an edge threshold of 0.125 (0.0312 in dark areas), and a
+    /// subpixel smoothing of 0.75.
+    pub fn new() -> Self {
+        Fxaa {
+            edge_threshold: 0.125,
+            edge_threshold_min: 0.0312,
+            subpixel: 0.75,
+        }
+    }
+}
+impl Default for Fxaa {
+    fn default() -> Self {
+        Fxaa::new()
+    }
+}
+impl PostEffect for Fxaa {
+    fn apply(&self, frame: &Frame, x: usize, y: usize) -> DVec4 {
+        let (x, y) = (x as i64, y as i64);
+        let center = frame.color(x, y);
+        let neighbor = |dx: i64, dy: i64| luma(frame.color(x + dx, y + dy));
+        let (m, n, s, e, w) = (
+            luma(center),
+            neighbor(0, -1),
+            neighbor(0, 1),
+            neighbor(1, 0),
+            neighbor(-1, 0),
+        );
+        // Skip the pixels that are not on an edge.
+        let max = m.max(n).max(s).max(e).max(w);
+        let range = max - m.min(n).min(s).min(e).min(w);
+        if range < self.edge_threshold_min.max(max * self.edge_threshold) {
+            return center;
+        }
+        let (nw, ne, sw, se) = (
+            neighbor(-1, -1),
+            neighbor(1, -1),
+            neighbor(-1, 1),
+            neighbor(1, 1),
+        );
+        // How much the pixel stands out from its neighborhood, to smooth single pixel details.
+        let average = (2.0 * (n + s + e + w) + nw + ne + sw + se) / 12.0;
+        let subpixel = ((average - m).abs() / range).clamp(0.0, 1.0);
+        let subpixel = subpixel * subpixel * (3.0 - 2.0 * subpixel);
+        let subpixel_blend = subpixel * subpixel * self.subpixel;
+        // Find whether the edge is horizontal or vertical, and on which side of the pixel it is.
+        let vertical_change =
+            (nw + sw - 2.0 * w).abs() + 2.0 * (n + s - 2.0 * m).abs() + (ne + se - 2.0 * e).abs();
+        let horizontal_change =
+            (nw + ne - 2.0 * n).abs() + 2.0 * (w + e - 2.0 * m).abs() + (sw + se - 2.0 * s).abs();
+        let horizontal = vertical_change >= horizontal_change;
+        let (tangent, normal, luma_1, luma_2) = if horizontal {
+            (DVec2::X, DVec2::Y, n, s)
+        } else {
+            (DVec2::Y, DVec2::X, w, e)
+        };
+        let (gradient_1, gradient_2) = ((luma_1 - m).abs(), (luma_2 - m).abs());
+        let (normal, luma_side) = if gradient_1 >= gradient_2 {
+            (-normal, luma_1)
+        } else {
+            (normal, luma_2)
+        };
+        let gradient = 0.25 * gradient_1.max(gradient_2);
+        let edge_luma = 0.5 * (luma_side + m);
+        // Walk along the edge in both directions until the contrast changes.
+        let pixel_center = DVec2::new(x as f64 + 0.5, y as f64 + 0.5);
+        let edge = pixel_center + normal * 0.5;
+        let find_end = |direction: DVec2| {
+            let mut delta = 0.0;
+            for step in 1..=FXAA_SEARCH_STEPS {
+                delta = luma(frame.sample(edge + direction * step as f64)) - edge_luma;
+                if delta.abs() >= gradient {
+                    return (step as f64, delta);
+                }
+            }
+            (FXAA_SEARCH_STEPS as f64, delta)
+        };
+        let (distance_1, delta_1) = find_end(-tangent);
+        let (distance_2, delta_2) = find_end(tangent);
+        let (distance, delta) = if distance_1 < distance_2 {
+            (distance_1, delta_1)
+        } else {
+            (distance_2, delta_2)
+        };
+        // Pixels close to the end of the edge are blended more, but only if the edge goes the
+        // other way than the pixel there.
+        let edge_blend = if (delta < 0.0) != (m < edge_luma) {
+            0.5 - distance / (distance_1 + distance_2)
+        } else {
+            0.0
+        };
+        frame.sample(pixel_center + normal * edge_blend.max(subpixel_blend))
+    }
+}
+/// Gamma correction. Brightens the midtones (for gamma values above 1) so that colors computed
+/// linearly look right on screen. Not needed when the pipeline already sRGB encodes its output
+/// (see [`super::Pipeline::set_srgb_output`]).
+pub struct Gamma {
+    /// The gamma of the display. Each color channel is raised to the power of 1/gamma.
+    pub gamma: f64,
+}
+impl Gamma {
+    /// Creates a gamma correction effect.
+    ///
+    /// # Arguments
+    ///
+    /// * `gamma` - The gamma of the display, usually 2.2.
+    pub fn new(gamma: f64) -> Self {
+        Gamma { gamma }
+    }
+}
+impl PostEffect for Gamma {
+    fn apply(&self, frame: &Frame, x: usize, y: usize) -> DVec4 {
+        let color = frame.color(x as i64, y as i64);
+        color.xyz().powf(1.0 / self.gamma).extend(color.w)
+    }
+}
+/// Darkens the frame towards its corners.
+pub struct Vignette {
+    /// How dark the corners get. A value between 0-1, where 1 makes them black.
+    pub strength: f64,
+    /// Distance from the center where the darkening starts. 0 is the center of the frame and 1
+    /// its corners.
+    pub radius: f64,
+    /// Distance over which the darkening goes from nothing to its full strength.
+    pub softness: f64,
+}
+impl Vignette {
+    /// Creates a vignette which starts halfway to the corners and fades in until them.
+    ///
+    /// # Arguments
+    ///
+    /// * `strength` - How dark the corners get. A value between 0-1, where 1 makes them black.
+    pub fn new(strength: f64) -> Self {
+        Vignette {
+            strength,
+            radius: 0.5,
+            softness: 0.5,
+        }
+    }
+}
+impl PostEffect for Vignette {
+    fn apply(&self, frame: &Frame, x: usize, y: usize) -> DVec4 {
+        let color = frame.color(x as i64, y as i64);
+        // Position relative to the center, between -1 and 1 along both axes.
+        let size = DVec2::new(frame.width() as f64, frame.height() as f64);
+        let position = (DVec2::new(x as f64 + 0.5, y as f64 + 0.5) / size - 0.5) * 2.0;
+        let distance = position.length() / 2f64.sqrt();
+        let t = ((distance - self.radius) / self.softness.max(f64::EPSILON)).clamp(0.0, 1.0);
+        let darkening = self.strength * t * t * (3.0 - 2.0 * t);
+        (color.xyz() * (1.0 - darkening)).extend(color.w)
+    }
+}
+/// Adjusts the colors of the frame. The adjustments are applied in the order of the fields.
+pub struct ColorGrading {
+    /// Color (RGB) multiplying the frame. Each channel is a value between 0-1.
+    pub tint: DVec3,
+    /// Value added to every color channel. 0 leaves the frame unchanged.
+    pub brightness: f64,
+    /// How far the colors are pushed away from middle gray. 1 leaves the frame unchanged and 0
+    /// makes it fully gray.
+    pub contrast: f64,
+    /// How colorful the frame is. 1 leaves the frame unchanged and 0 makes it grayscale.
+    pub saturation: f64,
+}
+impl ColorGrading {
+    /// Creates a color grading effect which leaves the frame unchanged.
+    pub fn new() -> Self {
+        ColorGrading {
+            tint: DVec3::ONE,
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+        }
+    }
+}
+impl Default for ColorGrading {
+    fn default() -> Self {
+        ColorGrading::new()
+    }
+}
+impl PostEffect for ColorGrading {
+    fn apply(&self, frame: &Frame, x: usize, y: usize) -> DVec4 {
+        let color = frame.color(x as i64, y as i64);
+        let graded = color.xyz() * self.tint + self.brightness;
+        let graded = (graded - 0.5) * self.contrast + 0.5;
+        let gray = DVec3::splat(graded.dot(LUMA_WEIGHTS));
+        gray.lerp(graded, self.saturation).extend(color.w)
+    }
+}
@@ -1,22 +1,48 @@
 //! Contains everything that will be needed to rasterize an image.
 use core::f64;
 
-use glam::{DVec2, DVec3, Vec3Swizzles, Vec4Swizzles};
+use glam::{DMat4, DVec2, DVec3, DVec4, U8Vec4, Vec3Swizzles, Vec4, Vec4Swizzles};
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefMutIterator, ParallelIterator};
 
 use crate::{
-    algorithm, graphics::screen::Screen, resources::texture::Texture, scene::light::Light,
+    algorithm,
+    graphics::{color, render_target::RenderTarget},
+    resources::{
+        material::{BlendMode, Material},
+        texture::{Texture, TextureCatalog},
+    },
+    scene::skybox::Skybox,
 };
 
 use super::{
     geometry::Geometry,
-    shader,
+    shader::{self, FrameFog, FrameLight, Lighting},
+    Hdr, Msaa,
 };
 
+/// Biggest number of samples per pixel (see [`Msaa`]).
+const MAX_SAMPLES: usize = 8;
+
 /// Holds the necessary values for rasterizing.
 pub struct Rasterizer {
     /// Divides the screen into tiles of size `tile_size`.
     tile_size: usize,
+    /// Width of the image the rasterizer will draw.
+    width: usize,
+    /// Height of the image the rasterizer will draw.
+    height: usize,
+    /// Number of samples per pixel used to smooth the edges of the triangles.
+    msaa: Msaa,
+    /// How the colors are accumulated in floating point, if they are, and brought back between
+    /// 0-1 when written to a render target.
+    hdr: Option<Hdr>,
+    /// Whether the colors are computed in linear space and sRGB encoded when written to a render
+    /// target. Otherwise, they are written as they are computed.
+    srgb_output: bool,
+    /// The fog the surfaces fade into, if there is one.
+    fog: Option<FrameFog>,
+    /// What reflective materials reflect, if anything.
+    environment: Option<Skybox>,
     /// The depth and pixel buffer for each tile on the screen.
     tiles: Vec<Tile>,
 }
@@ -28,129 +54,189 @@ impl Rasterizer {
     /// * `tile_size` - Size of the tiles the rasterizer will split the screen with.
     /// * `width` - Width of the screen the rasterizer will draw on.
     /// * `Height` - Height of the screen the rasterizer will draw on.
-    pub fn new(tile_size: usize, width: usize, height: usize) -> Self {
+    /// * `msaa` - Number of samples per pixel used to smooth the edges of the triangles.
+    pub fn new(tile_size: usize, width: usize, height: usize, msaa: Msaa) -> Self {
+        Rasterizer::with_buffers(tile_size, width, height, msaa, true)
+    }
+    /// Create a new rasterizer which only keeps depth values, such as the ones rendering shadow
+    /// maps. It has no color buffer, so it can only draw with
+    /// [`Rasterizer::rasterize_depth_threaded`].
+    ///
+    /// # Arguments
+    ///
+    /// * `tile_size` - Size of the tiles the rasterizer will split the screen with.
+    /// * `width` - Width of the screen the rasterizer will draw on.
+    /// * `height` - Height of the screen the rasterizer will draw on.
+    pub fn new_depth_only(tile_size: usize, width: usize, height: usize) -> Self {
+        Rasterizer::with_buffers(tile_size, width, height, Msaa::Off, false)
+    }
+    /// Create a new rasterizer, with or without a color buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `tile_size` - Size of the tiles the rasterizer will split the screen with.
+    /// * `width` - Width of the screen the rasterizer will draw on.
+    /// * `height` - Height of the screen the rasterizer will draw on.
+    /// * `msaa` - Number of samples per pixel used to smooth the edges of the triangles.
+    /// * `color` - Whether the tiles have a color buffer.
+    fn with_buffers(
+        tile_size: usize,
+        width: usize,
+        height: usize,
+        msaa: Msaa,
+        color: bool,
+    ) -> Self {
         // Figure out how many tiles are required given its size.
         let (nb_tiles_x, nb_tiles_y) = (
             (width + tile_size - 1) / tile_size,
             (height + tile_size - 1) / tile_size,
         );
-        // Initialize the tiles to be transparent black at every pixel with maximum depth.
+        // Initialize the tiles to be transparent black at every sample with maximum depth.
+        let nb_samples = msaa.nb_samples();
         let tiles = vec![
             Tile {
-                depth_buf: vec![f64::INFINITY; tile_size * tile_size],
-                frame_buf: vec![0; tile_size * tile_size * 4]
+                depth_buf: vec![f64::INFINITY; tile_size * tile_size * nb_samples],
+                frame_buf: if color {
+                    vec![0; tile_size * tile_size * 4 * nb_samples]
+                } else {
+                    Vec::new()
+                },
+                hdr_buf: Vec::new(),
             };
             nb_tiles_x * nb_tiles_y
         ];
-        Rasterizer { tile_size, tiles }
+        Rasterizer {
+            tile_size,
+            width,
+            height,
+            msaa,
+            hdr: None,
+            srgb_output: true,
+            fog: None,
+            environment: None,
+            tiles,
+        }
+    }
+    /// Sets whether colors are accumulated in floating point (HDR) instead of 8 bits per channel.
+    ///
+    /// In HDR, colors are neither clamped while shading nor while blending, so bright lights
+    /// keep their intensity until the image is written to a render target, where it is tone
+    /// mapped back between 0-1. The tiles are cleared to transparent black.
+    ///
+    /// # Arguments
+    ///
+    /// * `hdr` - How the colors are tone mapped. None stores 8 bit colors.
+    pub fn set_hdr(&mut self, hdr: Option<Hdr>) {
+        let nb_values = self.tile_size * self.tile_size * 4 * self.msaa.nb_samples();
+        for tile in self.tiles.iter_mut() {
+            // Only the buffer in use is allocated.
+            (tile.frame_buf, tile.hdr_buf) = match hdr {
+                Some(_) => (Vec::new(), vec![0.0; nb_values]),
+                None => (vec![0; nb_values], Vec::new()),
+            };
+        }
+        self.hdr = hdr;
+        self.clear();
     }
     /// Clears the tiles of the rasterizer to a transparent black.
     /// TODO: Add dirty tile system and only fill these up.
-    pub fn _clear(&mut self) {
+    pub fn clear(&mut self) {
         for tile in self.tiles.iter_mut() {
             tile.depth_buf.fill(f64::INFINITY);
             tile.frame_buf.fill(0);
+            tile.hdr_buf.fill(0.0);
         }
     }
     /// Clears the tiles of the rasterizer to a certain color.
     /// TODO: Add dirty tile system and only fill these up.
     pub fn clear_with_color(&mut self, color: &[u8]) {
         let color_alpha = [color[0], color[1], color[2], 255];
+        // HDR colors are linear.
+        let hdr_color_alpha = if self.srgb_output {
+            color::decode_srgb_color(U8Vec4::from_array(color_alpha))
+        } else {
+            U8Vec4::from_array(color_alpha).as_dvec4() / 255.0
+        };
+        let hdr_color_alpha = hdr_color_alpha.as_vec4().to_array();
         for tile in self.tiles.iter_mut() {
             tile.depth_buf.fill(f64::INFINITY);
             for value in tile.frame_buf.chunks_exact_mut(4) {
                 value.copy_from_slice(&color_alpha);
             }
+            for value in tile.hdr_buf.chunks_exact_mut(4) {
+                value.copy_from_slice(&hdr_color_alpha);
+            }
         }
     }
-    /// Raterizes the geometry on the screen buffer while making use of multithreading.
+    /// Raterizes the geometry in the tile buffers while making use of multithreading.
     ///
     /// Uses a tilling approach, where the screen is divided into different
     /// tiles of size `tile_size`² and each one is rasterized by a different using rayon.
     /// TILES GO LEFT TO RIGHT, TOP TO BOTTOM (Row major).
+    ///
+    /// Every shading input (colors, textures, highlights) is read from the `material`, whose
+    /// textures are looked up in the `textures` catalog.
+    ///
+    /// With multisampling, the coverage and depth are tested at every sample of a pixel, but the
+    /// pixel is only shaded once (at its center) and its color is written to the covered
+    /// samples.
+    ///
+    /// The result only ends up on screen once [`Rasterizer::write_to_target`] is called.
     pub fn rasterize_threaded(
         &mut self,
         geometry_screen: &Geometry,
-        screen: &mut Screen,
-        texture: Option<&Texture>,
+        material: &Material,
+        textures: &TextureCatalog,
         shader: &shader::Shader,
-        lights: &[Light],
+        view_point: &shader::ViewPoint,
+        lights: &[FrameLight],
     ) {
         let tile_size = self.tile_size();
         // Get useful values for rasterizing.
         let vertices_screen = geometry_screen.vertices();
         let uvs = geometry_screen.uvs();
+        let colors = geometry_screen.colors();
+        let vertex_shading = geometry_screen.vertex_shading();
+        let vertex_shadowed_shading = geometry_screen.vertex_shadowed_shading();
+        // Number of lights whose shadows are applied at every pixel.
+        let nb_shadowed = lights
+            .iter()
+            .filter(|light| light.shadow_map.is_some())
+            .count();
+        let normals = geometry_screen.normals();
+        let tangents = geometry_screen.tangents();
+        let world_positions = geometry_screen.world_positions();
         let w_invs = geometry_screen.clip_w_inv();
         let triangles = geometry_screen.triangles();
-        let triangle_world_normals = geometry_screen.triangle_normals();
-        let (width, height) = (screen.width(), screen.height());
-        // Get number of channels the texture format requries (0 if no texture).
-        let nb_channels = if let Some(t) = texture {
-            t.nb_chanels() as usize
-        } else {
-            0
+        let width = self.width;
+        // Get the textures of the material.
+        let material_texture = |id: Option<u32>| id.and_then(|id| textures.texture_from_id(id));
+        let diffuse_texture = material_texture(material.diffuse_texture);
+        let specular_texture = material_texture(material.specular_texture);
+        let emissive_texture = material_texture(material.emissive_texture);
+        let normal_texture = material_texture(material.normal_texture);
+        // The material's sampler takes precedence over the textures' own.
+        let texel_color = |texture: &Texture, uv: DVec2, uv_dx: DVec2, uv_dy: DVec2| {
+            material
+                .sampler
+                .unwrap_or(*texture.sampler())
+                .sample(texture, uv, uv_dx, uv_dy)
         };
+        let transparent = material.blend_mode.is_transparent();
+        // Positions of the samples within the pixels.
+        let sample_offsets = self.msaa.sample_offsets();
+        let nb_samples = sample_offsets.len();
+        let hdr = self.hdr.is_some();
+        let srgb_output = self.srgb_output;
+        let fog = self.fog;
+        let environment = self.environment;
 
-        // Figure out how many tiles are required given its size.
-        let (nb_tiles_x, nb_tiles_y) = (
-            (width + tile_size - 1) / tile_size,
-            (height + tile_size - 1) / tile_size,
-        );
+        // Figure out how many tiles are in a row.
+        let nb_tiles_x = width.div_ceil(tile_size);
 
-        // Create tile buffers for the f        // Bin the triangles into the different tiles.
-        let mut binned_triangles: Vec<Vec<BinnedTriangle>> =
-            vec![Vec::new(); nb_tiles_x * nb_tiles_y];
+        // Bin the triangles into the different tiles.
+        let binned_triangles = self.bin_triangles(geometry_screen);
 
-        // Start binning the triangles.
-        for triangle_index_start in (0..triangles.len()).step_by(3) {
-            // Triangle vertex indices.
-            let (ai, bi, ci) = (
-                triangles[triangle_index_start],
-                triangles[triangle_index_start + 1],
-                triangles[triangle_index_start + 2],
-            );
-            // Triangle vertex position in space.
-            let (a, b, c) = (
-                vertices_screen[ai].xyz(),
-                vertices_screen[bi].xyz(),
-                vertices_screen[ci].xyz(),
-            );
-
-            // Get bounding box of triangle.
-            // Both max and min values are included
-            let (min_xf64, max_xf64, min_yf64, max_yf64) =
-                algorithm::triangle_aabs(a.xy(), b.xy(), c.xy());
-            // Ensure they don't cross the screen's border, and convert them to
-            // integer screen coordinates.
-            let min_x = min_xf64.max(0.0) as usize;
-            let min_y = min_yf64.max(0.0) as usize;
-            let max_x = (max_xf64 as usize).min(width - 1);
-            let max_y = (max_yf64 as usize).min(height - 1);
-
-            // Given the triangle's corner positions, find which tiles it intersects.
-            // Both first and last tiles are inclusive.
-            let (first_tile_x, last_tile_x, first_tile_y, last_tile_y) = (
-                min_x / tile_size,
-                max_x / tile_size,
-                min_y / tile_size,
-                max_y / tile_size,
-            );
-            // Add the triangle to the bin of each tile.
-            for tile_y in first_tile_y..=last_tile_y {
-                for tile_x in first_tile_x..=last_tile_x {
-                    let mut binned_triangle = BinnedTriangle::new();
-                    // Get the relative position of the aabs within the tile.
-                    binned_triangle.min_x = min_x - (tile_x * tile_size).min(min_x);
-                    binned_triangle.min_y = min_y - (tile_y * tile_size).min(min_y);
-                    binned_triangle.max_x = (max_x - tile_x * tile_size).min(tile_size - 1);
-                    binned_triangle.max_y = (max_y - tile_y * tile_size).min(tile_size - 1);
-                    binned_triangle.triangle_start = triangle_index_start;
-                    binned_triangle.world_normal = triangle_world_normals[triangle_index_start/3];
-                    // Push it in the corresponding bin.
-                    binned_triangles[tile_x + tile_y * nb_tiles_x].push(binned_triangle);
-                }
-            }
-        }
         // Rasterize in parallel on each tile.
         // frame_buffers
         //     .par_iter_mut()
@@ -159,7 +245,14 @@ impl Rasterizer {
             .par_iter_mut()
             .enumerate()
             .for_each(|(tile_nb, tile)| {
-                let (tile_frame_buffer, tile_depth_buf) = tile.get_buffers();
+                let Tile {
+                    frame_buf: tile_frame_buffer,
+                    hdr_buf: tile_hdr_buffer,
+                    depth_buf: tile_depth_buf,
+                } = tile;
+                // Light reaching the triangle from each light with a shadow map, with flat
+                // shading.
+                let mut flat_shadowed = Vec::with_capacity(nb_shadowed);
                 // Obtain the tile's coordinate from the tile number.
                 let x_offset = (tile_nb % nb_tiles_x) * tile_size;
                 let y_offset = (tile_nb / nb_tiles_x) * tile_size;
@@ -186,19 +279,95 @@ impl Rasterizer {
                     );
                     // UV coordinates of each vertex.
                     let (uv_a, uv_b, uv_c) = (uvs[ai], uvs[bi], uvs[ci]);
+                    // Color of each vertex.
+                    let (color_a, color_b, color_c) = (colors[ai], colors[bi], colors[ci]);
                     // Inverted w (1/w) from the homogeneous coordinates in clip space.
                     let (w_inv_a, w_inv_b, w_inv_c) = (w_invs[ai], w_invs[bi], w_invs[ci]);
+                    // Center of the triangle in world space.
+                    let center =
+                        (world_positions[ai] + world_positions[bi] + world_positions[ci]) / 3.0;
+                    // Double sided materials are lit on the side facing the viewer.
+                    let facing = if material.double_sided
+                        && triangle_normal.dot(view_point.direction_to_eye(center)) < 0.0
+                    {
+                        -1.0
+                    } else {
+                        1.0
+                    };
+                    // Lighting of the whole triangle when using flat shading, and of each vertex
+                    // when using Gouraud shading.
+                    let (flat_shading, shading_a, shading_b, shading_c) = match shader.shader_type {
+                        shader::ShaderType::Flat => {
+                            // The triangle is lit as seen from its center. The shadows are
+                            // applied at every pixel.
+                            flat_shadowed.clear();
+                            let shading = shader.shade_without_shadows(
+                                facing * triangle_normal,
+                                center,
+                                view_point,
+                                material,
+                                lights,
+                                &mut flat_shadowed,
+                            );
+                            (
+                                shading,
+                                Lighting::default(),
+                                Lighting::default(),
+                                Lighting::default(),
+                            )
+                        }
+                        shader::ShaderType::Gouraud => (
+                            Lighting::default(),
+                            vertex_shading[ai],
+                            vertex_shading[bi],
+                            vertex_shading[ci],
+                        ),
+                        shader::ShaderType::Phong => (
+                            Lighting::default(),
+                            Lighting::default(),
+                            Lighting::default(),
+                            Lighting::default(),
+                        ),
+                    };
 
                     // The barycentric coordinate gradients.
                     let (alpha_grad, beta_grad, gamma_grad) =
                         algorithm::barycentric_gradients2(a.xy(), b.xy(), c.xy());
                     // Initialize the important values' derivatives.
-                    let depth_dx = alpha_grad.x * a.z + beta_grad.x * b.z + gamma_grad.x * c.z;
+                    let depth_grad = DVec2::new(
+                        alpha_grad.x * a.z + beta_grad.x * b.z + gamma_grad.x * c.z,
+                        alpha_grad.y * a.z + beta_grad.y * b.z + gamma_grad.y * c.z,
+                    );
                     let w_inv_dx =
                         alpha_grad.x * w_inv_a + beta_grad.x * w_inv_b + gamma_grad.x * w_inv_c; // Used for interpolation.
                     let uv_over_w_dx = alpha_grad.x * uv_a * w_inv_a
                         + beta_grad.x * uv_b * w_inv_b
                         + gamma_grad.x * uv_c * w_inv_c;
+                    let color_over_w_dx = alpha_grad.x * color_a * w_inv_a
+                        + beta_grad.x * color_b * w_inv_b
+                        + gamma_grad.x * color_c * w_inv_c;
+                    let diffuse_over_w_dx = alpha_grad.x * shading_a.diffuse * w_inv_a
+                        + beta_grad.x * shading_b.diffuse * w_inv_b
+                        + gamma_grad.x * shading_c.diffuse * w_inv_c;
+                    let specular_over_w_dx = alpha_grad.x * shading_a.specular * w_inv_a
+                        + beta_grad.x * shading_b.specular * w_inv_b
+                        + gamma_grad.x * shading_c.specular * w_inv_c;
+                    // Vertical derivatives, used to pick the textures' level of detail and to
+                    // move the shading point inside the triangle with multisampling.
+                    let w_inv_dy =
+                        alpha_grad.y * w_inv_a + beta_grad.y * w_inv_b + gamma_grad.y * w_inv_c;
+                    let uv_over_w_dy = alpha_grad.y * uv_a * w_inv_a
+                        + beta_grad.y * uv_b * w_inv_b
+                        + gamma_grad.y * uv_c * w_inv_c;
+                    let color_over_w_dy = alpha_grad.y * color_a * w_inv_a
+                        + beta_grad.y * color_b * w_inv_b
+                        + gamma_grad.y * color_c * w_inv_c;
+                    let diffuse_over_w_dy = alpha_grad.y * shading_a.diffuse * w_inv_a
+                        + beta_grad.y * shading_b.diffuse * w_inv_b
+                        + gamma_grad.y * shading_c.diffuse * w_inv_c;
+                    let specular_over_w_dy = alpha_grad.y * shading_a.specular * w_inv_a
+                        + beta_grad.y * shading_b.specular * w_inv_b
+                        + gamma_grad.y * shading_c.specular * w_inv_c;
 
                     // Get bounding box of triangle within the tile.
                     let min_x = binned_triangle.min_x;
@@ -235,64 +404,238 @@ impl Rasterizer {
                             + beta_xy * uv_b * w_inv_b
                             + gamma_xy * uv_c * w_inv_c; // Weird value, but useful given its linear
                                                          // properties in screen space.
+                        let mut color_over_w = alpha_xy * color_a * w_inv_a
+                            + beta_xy * color_b * w_inv_b
+                            + gamma_xy * color_c * w_inv_c;
+                        let mut diffuse_over_w = alpha_xy * shading_a.diffuse * w_inv_a
+                            + beta_xy * shading_b.diffuse * w_inv_b
+                            + gamma_xy * shading_c.diffuse * w_inv_c;
+                        let mut specular_over_w = alpha_xy * shading_a.specular * w_inv_a
+                            + beta_xy * shading_b.specular * w_inv_b
+                            + gamma_xy * shading_c.specular * w_inv_c;
                         for _ in min_x..=max_x {
-                            // Check if pixel is inside the triangle.
-                            // &&
-                            // Make sure pixels closer to the screen have not been been drawn.
-                            // Smaller depth means closer to screen.
-                            if ((alpha_xy >= 0.0) & (beta_xy >= 0.0) & (gamma_xy >= 0.0))
-                                && depth < tile_depth_buf[pixel_index]
-                            {
-                                tile_depth_buf[pixel_index] = depth;
-
+                            // Check which samples of the pixel are inside the triangle and not
+                            // hidden by what is already drawn.
+                            let first_sample = pixel_index * nb_samples;
+                            let (coverage, sample_depths) = covered_samples(
+                                DVec3::new(alpha_xy, beta_xy, gamma_xy),
+                                (alpha_grad, beta_grad, gamma_grad),
+                                depth,
+                                depth_grad,
+                                sample_offsets,
+                                &tile_depth_buf[first_sample..first_sample + nb_samples],
+                            );
+                            if coverage != 0 {
+                                // Shade at the center of the pixel, unless it is outside of the
+                                // triangle. The first covered sample is then used instead, so
+                                // that the values of the triangle are not extrapolated.
+                                let offset =
+                                    if (alpha_xy >= 0.0) & (beta_xy >= 0.0) & (gamma_xy >= 0.0) {
+                                        DVec2::ZERO
+                                    } else {
+                                        sample_offsets[coverage.trailing_zeros() as usize]
+                                    };
+                                let (alpha_xy, beta_xy, gamma_xy) = (
+                                    alpha_xy + alpha_grad.dot(offset),
+                                    beta_xy + beta_grad.dot(offset),
+                                    gamma_xy + gamma_grad.dot(offset),
+                                );
+                                let w_inv = w_inv + w_inv_dx * offset.x + w_inv_dy * offset.y;
+                                let uv_over_w =
+                                    uv_over_w + uv_over_w_dx * offset.x + uv_over_w_dy * offset.y;
+                                let color_over_w = color_over_w
+                                    + color_over_w_dx * offset.x
+                                    + color_over_w_dy * offset.y;
+                                let diffuse_over_w = diffuse_over_w
+                                    + diffuse_over_w_dx * offset.x
+                                    + diffuse_over_w_dy * offset.y;
+                                let specular_over_w = specular_over_w
+                                    + specular_over_w_dx * offset.x
+                                    + specular_over_w_dy * offset.y;
                                 // Get the UV coordinates of the pixel.
                                 let uv = uv_over_w / w_inv;
-
-                                // Given the UV coordinates, get the texture color and draw it.
-                                let pixel_channel_index = 4 * pixel_index;
-                                match texture {
+                                // How much the UV coordinates change from one pixel to the next.
+                                let uv_dx = (uv_over_w_dx - uv * w_inv_dx) / w_inv;
+                                let uv_dy = (uv_over_w_dy - uv * w_inv_dy) / w_inv;
+                                // Get the interpolated color of the vertices.
+                                let vertex_color = color_over_w / w_inv;
+                                // Given the UV coordinates, get the material's colors.
+                                let albedo = match diffuse_texture {
                                     Some(texture) => {
-                                        let shading_value = match shader.shader_type {
-                                            shader::ShaderType::Flat => {
-                                                shader.shade(triangle_normal, lights)
-                                            }
-                                            shader::ShaderType::Phong => {
-                                                todo!("Implement Phong shading")
-                                            }
-                                            shader::ShaderType::Gouraud => {
-                                                todo!("Implement Gouraud shading.")
-                                            }
-                                        };
-                                        let color: Vec<u8> = texture
-                                            .from_uv(uv[0], uv[1])
-                                            .to_vec()
-                                            .iter_mut()
-                                            .map(|&mut a| (a as f64 * shading_value) as u8)
-                                            .collect();
-                                        // SAFETY: frame is guaranteed to have at least 4 valid indices
-                                        // after pixel_channel_index, and color has at most 4. Thus,
-                                        // when copying, nothing will go out of bounds.
-                                        unsafe {
-                                            std::ptr::copy_nonoverlapping(
-                                                color.as_ptr(),
-                                                tile_frame_buffer
-                                                    .as_mut_ptr()
-                                                    .add(pixel_channel_index),
-                                                nb_channels,
-                                            );
+                                        material.base_color
+                                            * vertex_color
+                                            * texel_color(texture, uv, uv_dx, uv_dy)
+                                    }
+                                    None => material.base_color * vertex_color,
+                                };
+                                // Cut out pixels are discarded before their depth is written, so
+                                // they never hide what is behind them.
+                                let cut_out = material
+                                    .alpha_cutoff
+                                    .is_some_and(|cutoff| albedo.w < cutoff);
+                                if !cut_out {
+                                    // Perspective correct barycentric coordinates.
+                                    let (alpha, beta, gamma) = (
+                                        alpha_xy * w_inv_a / w_inv,
+                                        beta_xy * w_inv_b / w_inv,
+                                        gamma_xy * w_inv_c / w_inv,
+                                    );
+                                    let position = alpha * world_positions[ai]
+                                        + beta * world_positions[bi]
+                                        + gamma * world_positions[ci];
+                                    // Normal of the surface at the pixel. Only per-pixel
+                                    // shading interpolates it, the others use the triangle's.
+                                    let normal = match shader.shader_type {
+                                        shader::ShaderType::Phong => {
+                                            let normal = (alpha * normals[ai]
+                                                + beta * normals[bi]
+                                                + gamma * normals[ci])
+                                                .normalize_or_zero();
+                                            // Add the details of the normal map.
+                                            let normal = match normal_texture {
+                                                Some(texture) if !tangents.is_empty() => {
+                                                    let tangent = alpha * tangents[ai]
+                                                        + beta * tangents[bi]
+                                                        + gamma * tangents[ci];
+                                                    let map_color =
+                                                        texel_color(texture, uv, uv_dx, uv_dy);
+                                                    perturb_normal(normal, tangent, map_color.xyz())
+                                                }
+                                                _ => normal,
+                                            };
+                                            facing * normal
                                         }
-                                        // If texture didn't have an alpha channel, use max alpha.
-                                        if nb_channels != 4 {
-                                            tile_frame_buffer[pixel_channel_index + 3] = 255;
+                                        _ => facing * triangle_normal,
+                                    };
+                                    // Get the light reaching the pixel.
+                                    let lighting = match shader.shader_type {
+                                        shader::ShaderType::Flat => shader::apply_shadows(
+                                            flat_shading,
+                                            flat_shadowed.iter().copied(),
+                                            normal,
+                                            position,
+                                            lights,
+                                        ),
+                                        shader::ShaderType::Phong => shader
+                                            .shade(normal, position, view_point, material, lights),
+                                        shader::ShaderType::Gouraud => {
+                                            let lighting = Lighting {
+                                                diffuse: diffuse_over_w / w_inv,
+                                                specular: specular_over_w / w_inv,
+                                            };
+                                            // The light of each light with a shadow map is
+                                            // interpolated like the rest of the lighting.
+                                            let shadowed = (0..nb_shadowed).map(|light| {
+                                                let shading = |vertex: usize| {
+                                                    vertex_shadowed_shading
+                                                        [vertex * nb_shadowed + light]
+                                                };
+                                                shading(ai) * alpha
+                                                    + shading(bi) * beta
+                                                    + shading(ci) * gamma
+                                            });
+                                            shader::apply_shadows(
+                                                lighting, shadowed, normal, position, lights,
+                                            )
+                                        }
+                                    };
+                                    let specular_color = specular_texture
+                                        .map_or(DVec3::ONE, |texture| {
+                                            texel_color(texture, uv, uv_dx, uv_dy).xyz()
+                                        });
+                                    let emission =
+                                        emissive_texture.map_or(material.emissive, |texture| {
+                                            material.emissive
+                                                * texel_color(texture, uv, uv_dx, uv_dy).xyz()
+                                        });
+                                    let diffuse_color = albedo.xyz() * lighting.diffuse;
+                                    // Reflective surfaces mirror the environment, more so at
+                                    // grazing angles.
+                                    let diffuse_color = match &environment {
+                                        Some(environment) if material.reflectivity > 0.0 => {
+                                            let to_eye = view_point.direction_to_eye(position);
+                                            let cos_theta = normal.dot(to_eye);
+                                            let reflected = normal * 2.0 * cos_theta - to_eye;
+                                            diffuse_color.lerp(
+                                                environment.color(reflected, textures),
+                                                shader::fresnel(material.reflectivity, cos_theta),
+                                            )
+                                        }
+                                        _ => diffuse_color,
+                                    };
+                                    // Only the color channels are lit. They can only go above
+                                    // 1 in HDR.
+                                    let lit_color = diffuse_color
+                                        + specular_color * lighting.specular
+                                        + emission;
+                                    let lit_color = if hdr {
+                                        lit_color
+                                    } else {
+                                        lit_color.min(DVec3::ONE)
+                                    };
+                                    // Fade the surface into the fog. In LDR, the surface is
+                                    // clamped first so that overbright surfaces fade like the
+                                    // color they are displayed with.
+                                    let lit_color = match &fog {
+                                        Some(fog) => {
+                                            fog.apply(lit_color, position, w_inv, view_point)
+                                        }
+                                        None => lit_color,
+                                    };
+                                    let alpha = (albedo.w * material.opacity).clamp(0.0, 1.0);
+                                    let source = lit_color.extend(alpha);
+                                    // The last 8 bit color blended, and what was behind it. It
+                                    // is reused by the samples showing the same thing, so that
+                                    // the color is usually encoded once per pixel.
+                                    let mut last_blend: Option<(U8Vec4, U8Vec4)> = None;
+                                    let samples = sample_depths.iter().take(nb_samples);
+                                    for (sample, &sample_depth) in samples.enumerate() {
+                                        if coverage & (1 << sample) == 0 {
+                                            continue;
+                                        }
+                                        let sample_index = first_sample + sample;
+                                        // Transparent surfaces do not hide what is drawn after
+                                        // them.
+                                        if !transparent {
+                                            tile_depth_buf[sample_index] = sample_depth;
+                                        }
+                                        // Combine the color with what is already drawn.
+                                        let pixel_range = 4 * sample_index..4 * sample_index + 4;
+                                        if hdr {
+                                            let sample_pixel = &mut tile_hdr_buffer[pixel_range];
+                                            let destination =
+                                                Vec4::from_slice(sample_pixel).as_dvec4();
+                                            let color =
+                                                material.blend_mode.blend(source, destination);
+                                            sample_pixel
+                                                .copy_from_slice(&color.as_vec4().to_array());
+                                        } else {
+                                            let sample_pixel = &mut tile_frame_buffer[pixel_range];
+                                            let destination = U8Vec4::from_slice(sample_pixel);
+                                            // Opaque surfaces hide what is behind them, so all
+                                            // their samples get the same color.
+                                            let color = match last_blend {
+                                                Some((last_destination, color))
+                                                    if !transparent
+                                                        || last_destination == destination =>
+                                                {
+                                                    color
+                                                }
+                                                _ => {
+                                                    let color = blend_8_bit(
+                                                        material.blend_mode,
+                                                        source,
+                                                        destination,
+                                                        srgb_output,
+                                                    );
+                                                    last_blend = Some((destination, color));
+                                                    color
+                                                }
+                                            };
+                                            sample_pixel.copy_from_slice(&color.to_array());
                                         }
                                     }
-                                    // Black if no texture.
-                                    None => {
-                                        tile_frame_buffer
-                                            [pixel_channel_index..pixel_channel_index + 4]
-                                            .copy_from_slice(&[0, 0, 0, 255]);
-                                    }
-                                };
+                                }
                             }
                             // Update barycentric coordinates for next horizontal pixel.
                             alpha_xy += alpha_grad.x;
@@ -300,9 +643,12 @@ impl Rasterizer {
                             gamma_xy += gamma_grad.x;
 
                             // Update important values with their derivatives for the next horizontal pixel.
-                            depth += depth_dx;
+                            depth += depth_grad.x;
                             w_inv += w_inv_dx;
                             uv_over_w += uv_over_w_dx;
+                            color_over_w += color_over_w_dx;
+                            diffuse_over_w += diffuse_over_w_dx;
+                            specular_over_w += specular_over_w_dx;
 
                             pixel_index += 1;
                         }
@@ -313,40 +659,350 @@ impl Rasterizer {
                     }
                 }
             });
-        // Write back to the main frame buffer.
-        let frame = screen.pixels_mut().unwrap().frame_mut();
-        let tiles = self.tiles_mut();
-        for tile_y in 0..nb_tiles_y {
-            let mut tile_nb = tile_y * nb_tiles_x;
-            for tile_x in 0..nb_tiles_x {
-                // Get references to the buffers.
-                let tile = &tiles[tile_nb];
-                let tile_frame_buffer: &[u8] = &tile.frame_buf;
-
-                // Get pixel offset.
-                let first_pixel_index = tile_x * tile_size + tile_y * tile_size * width;
-
-                // For each row in the tile, copy it over to the main frame buffer.
-                for tile_row in 0..tile_size {
-                    // Check if you are going beyond the screen's height.
-                    if tile_row + tile_y * tile_size >= height {
-                        break;
+    }
+    /// Rasterizes only the depth of the geometry in the tile buffers, while making use of
+    /// multithreading.
+    ///
+    /// Used to render the scene from the point of view of a light, where colors are not needed.
+    /// The depth ends up in [`Rasterizer::depth_buffer`].
+    pub fn rasterize_depth_threaded(&mut self, geometry_screen: &Geometry) {
+        let tile_size = self.tile_size();
+        let vertices_screen = geometry_screen.vertices();
+        let triangles = geometry_screen.triangles();
+        let nb_tiles_x = self.width.div_ceil(tile_size);
+        let sample_offsets = self.msaa.sample_offsets();
+        let nb_samples = sample_offsets.len();
+        let binned_triangles = self.bin_triangles(geometry_screen);
+        self.tiles_mut()
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(tile_nb, tile)| {
+                let tile_depth_buf = &mut tile.depth_buf;
+                // Obtain the tile's coordinate from the tile number.
+                let x_offset = (tile_nb % nb_tiles_x) * tile_size;
+                let y_offset = (tile_nb / nb_tiles_x) * tile_size;
+                for binned_triangle in binned_triangles[tile_nb].iter() {
+                    let triangle_index_start = binned_triangle.triangle_start;
+                    // Triangle's vertex positions in screen space.
+                    let (a, b, c) = (
+                        vertices_screen[triangles[triangle_index_start]].xyz(),
+                        vertices_screen[triangles[triangle_index_start + 1]].xyz(),
+                        vertices_screen[triangles[triangle_index_start + 2]].xyz(),
+                    );
+                    // The gradients of slivers (often left by the clipping, or seen edge-on from
+                    // the light) are too imprecise to rasterize them, and they cover next to no
+                    // pixels anyway.
+                    if (b.xy() - a.xy()).perp_dot(c.xy() - a.xy()).abs() < 0.01 {
+                        continue;
+                    }
+                    let (alpha_grad, beta_grad, gamma_grad) =
+                        algorithm::barycentric_gradients2(a.xy(), b.xy(), c.xy());
+                    let depth_grad = DVec2::new(
+                        alpha_grad.x * a.z + beta_grad.x * b.z + gamma_grad.x * c.z,
+                        alpha_grad.y * a.z + beta_grad.y * b.z + gamma_grad.y * c.z,
+                    );
+                    // Barycentric coordinates at the center of the top left pixel of the aabs.
+                    let min_posf64_screen = DVec2::new(
+                        (x_offset + binned_triangle.min_x) as f64 + 0.5,
+                        (y_offset + binned_triangle.min_y) as f64 + 0.5,
+                    );
+                    let (mut alpha_0y, mut beta_0y, mut gamma_0y) = (
+                        alpha_grad.dot(min_posf64_screen - c.xy()),
+                        beta_grad.dot(min_posf64_screen - a.xy()),
+                        gamma_grad.dot(min_posf64_screen - b.xy()),
+                    );
+                    for y in binned_triangle.min_y..=binned_triangle.max_y {
+                        let row_start = y * tile_size;
+                        let (mut alpha_xy, mut beta_xy, mut gamma_xy) =
+                            (alpha_0y, beta_0y, gamma_0y);
+                        let mut depth = alpha_xy * a.z + beta_xy * b.z + gamma_xy * c.z;
+                        let row = &mut tile_depth_buf[(row_start + binned_triangle.min_x)
+                            * nb_samples
+                            ..(row_start + binned_triangle.max_x + 1) * nb_samples];
+                        for pixel_depths in row.chunks_exact_mut(nb_samples) {
+                            let (coverage, sample_depths) = covered_samples(
+                                DVec3::new(alpha_xy, beta_xy, gamma_xy),
+                                (alpha_grad, beta_grad, gamma_grad),
+                                depth,
+                                depth_grad,
+                                sample_offsets,
+                                pixel_depths,
+                            );
+                            for (sample, pixel_depth) in pixel_depths.iter_mut().enumerate() {
+                                if coverage & (1 << sample) != 0 {
+                                    *pixel_depth = sample_depths[sample];
+                                }
+                            }
+                            alpha_xy += alpha_grad.x;
+                            beta_xy += beta_grad.x;
+                            gamma_xy += gamma_grad.x;
+                            depth += depth_grad.x;
+                        }
+                        alpha_0y += alpha_grad.y;
+                        beta_0y += beta_grad.y;
+                        gamma_0y += gamma_grad.y;
                     }
-                    // Ensure you don't copy too far on the screen and end up
-                    // wrapping/out of bounds.
-                    let pixels_to_copy = (width - tile_x * tile_size).min(tile_size);
-                    unsafe {
-                        std::ptr::copy_nonoverlapping(
-                            tile_frame_buffer.as_ptr().add(tile_row * tile_size * 4),
-                            frame
-                                .as_mut_ptr()
-                                .add((first_pixel_index + tile_row * width) * 4),
-                            pixels_to_copy * 4,
-                        );
+                }
+            });
+    }
+    /// Draws the skybox on every sample nothing was drawn on yet, so it ends up behind what is
+    /// already drawn.
+    ///
+    /// The direction of the sky is found at the center of each pixel, by sending a ray from the
+    /// near plane to the far plane of the camera.
+    ///
+    /// # Arguments
+    ///
+    /// * `skybox` - The skybox.
+    /// * `textures` - The textures the skybox references.
+    /// * `screen_to_world` - Transform from screen space (with the depth of ndc space) to world
+    ///   space.
+    pub fn draw_skybox(
+        &mut self,
+        skybox: &Skybox,
+        textures: &TextureCatalog,
+        screen_to_world: &DMat4,
+    ) {
+        let tile_size = self.tile_size;
+        let (width, height) = (self.width, self.height);
+        let nb_tiles_x = width.div_ceil(tile_size);
+        let nb_samples = self.msaa.nb_samples();
+        let (hdr, srgb_output) = (self.hdr.is_some(), self.srgb_output);
+        self.tiles_mut()
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(tile_nb, tile)| {
+                let Tile {
+                    frame_buf: tile_frame_buffer,
+                    hdr_buf: tile_hdr_buffer,
+                    depth_buf: tile_depth_buf,
+                } = tile;
+                let x_offset = (tile_nb % nb_tiles_x) * tile_size;
+                let y_offset = (tile_nb / nb_tiles_x) * tile_size;
+                for y in 0..tile_size.min(height - y_offset) {
+                    for x in 0..tile_size.min(width - x_offset) {
+                        let first_sample = (x + y * tile_size) * nb_samples;
+                        let samples = first_sample..first_sample + nb_samples;
+                        // Only the samples at the maximum depth are empty.
+                        if tile_depth_buf[samples.clone()]
+                            .iter()
+                            .all(|depth| depth.is_finite())
+                        {
+                            continue;
+                        }
+                        let pixel = DVec2::new((x + x_offset) as f64, (y + y_offset) as f64) + 0.5;
+                        let near = screen_to_world.project_point3(pixel.extend(-1.0));
+                        let far = screen_to_world.project_point3(pixel.extend(1.0));
+                        let sky_color = skybox.color(far - near, textures).extend(1.0);
+                        for sample in samples.filter(|&sample| tile_depth_buf[sample].is_infinite())
+                        {
+                            let sample_range = 4 * sample..4 * sample + 4;
+                            if hdr {
+                                tile_hdr_buffer[sample_range]
+                                    .copy_from_slice(&sky_color.as_vec4().to_array());
+                            } else {
+                                let color = if srgb_output {
+                                    color::encode_srgb_color(sky_color)
+                                } else {
+                                    (sky_color.clamp(DVec4::ZERO, DVec4::ONE) * 255.0).as_u8vec4()
+                                };
+                                tile_frame_buffer[sample_range].copy_from_slice(&color.to_array());
+                            }
+                        }
                     }
                 }
-                // Go to next tile.
-                tile_nb += 1;
+            });
+    }
+    /// Bins the triangles of the geometry into the tiles they overlap.
+    ///
+    /// # Arguments
+    ///
+    /// * `geometry_screen` - The geometry to bin, in screen space.
+    ///
+    /// # Return
+    ///
+    /// The triangles overlapping each tile, in the order the tiles are stored.
+    fn bin_triangles(&self, geometry_screen: &Geometry) -> Vec<Vec<BinnedTriangle>> {
+        let tile_size = self.tile_size();
+        let vertices_screen = geometry_screen.vertices();
+        let triangles = geometry_screen.triangles();
+        let triangle_world_normals = geometry_screen.triangle_normals();
+        let (width, height) = (self.width, self.height);
+        let (nb_tiles_x, nb_tiles_y) = (width.div_ceil(tile_size), height.div_ceil(tile_size));
+
+        let mut binned_triangles: Vec<Vec<BinnedTriangle>> =
+            vec![Vec::new(); nb_tiles_x * nb_tiles_y];
+
+        // Start binning the triangles.
+        for triangle_index_start in (0..triangles.len()).step_by(3) {
+            // Triangle vertex indices.
+            let (ai, bi, ci) = (
+                triangles[triangle_index_start],
+                triangles[triangle_index_start + 1],
+                triangles[triangle_index_start + 2],
+            );
+            // Triangle vertex position in space.
+            let (a, b, c) = (
+                vertices_screen[ai].xyz(),
+                vertices_screen[bi].xyz(),
+                vertices_screen[ci].xyz(),
+            );
+
+            // Get bounding box of triangle.
+            // Both max and min values are included
+            let (min_xf64, max_xf64, min_yf64, max_yf64) =
+                algorithm::triangle_aabs(a.xy(), b.xy(), c.xy());
+            // Ensure they don't cross the screen's border, and convert them to
+            // integer screen coordinates.
+            let min_x = min_xf64.max(0.0) as usize;
+            let min_y = min_yf64.max(0.0) as usize;
+            let max_x = (max_xf64 as usize).min(width - 1);
+            let max_y = (max_yf64 as usize).min(height - 1);
+
+            // Given the triangle's corner positions, find which tiles it intersects.
+            // Both first and last tiles are inclusive.
+            let (first_tile_x, last_tile_x, first_tile_y, last_tile_y) = (
+                min_x / tile_size,
+                max_x / tile_size,
+                min_y / tile_size,
+                max_y / tile_size,
+            );
+            // Add the triangle to the bin of each tile.
+            for tile_y in first_tile_y..=last_tile_y {
+                for tile_x in first_tile_x..=last_tile_x {
+                    let mut binned_triangle = BinnedTriangle::new();
+                    // Get the relative position of the aabs within the tile.
+                    binned_triangle.min_x = min_x - (tile_x * tile_size).min(min_x);
+                    binned_triangle.min_y = min_y - (tile_y * tile_size).min(min_y);
+                    binned_triangle.max_x = (max_x - tile_x * tile_size).min(tile_size - 1);
+                    binned_triangle.max_y = (max_y - tile_y * tile_size).min(tile_size - 1);
+                    binned_triangle.triangle_start = triangle_index_start;
+                    // Geometry rendered without shading has no triangle normals.
+                    binned_triangle.world_normal = triangle_world_normals
+                        .get(triangle_index_start / 3)
+                        .copied()
+                        .unwrap_or_default();
+                    // Push it in the corresponding bin.
+                    binned_triangles[tile_x + tile_y * nb_tiles_x].push(binned_triangle);
+                }
+            }
+        }
+        binned_triangles
+    }
+    /// Copies the content of the tiles into a render target.
+    ///
+    /// The color of every tile is written to the target's color buffer and, if the target keeps
+    /// depth values, the depth of every tile is written to its depth buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Where the rasterized image will be written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the target's dimensions differ from the rasterizer's.
+    pub fn write_to_target(&self, target: &mut dyn RenderTarget) {
+        let tile_size = self.tile_size();
+        let (width, height) = (self.width, self.height);
+        assert!(
+            target.width() == width && target.height() == height,
+            "Render target ({}x{}) does not match the rasterizer ({}x{}).",
+            target.width(),
+            target.height(),
+            width,
+            height
+        );
+        let nb_tiles_x = width.div_ceil(tile_size);
+        // Write back to the main frame buffer, tone mapping the colors in HDR.
+        if let Some(frame) = target.color_buffer_mut() {
+            let srgb_output = self.srgb_output;
+            match &self.hdr {
+                Some(hdr) => self.copy_tiles(
+                    frame,
+                    nb_tiles_x,
+                    4,
+                    |tile| &tile.hdr_buf,
+                    |s, p| resolve_hdr_color(s, p, hdr, srgb_output),
+                ),
+                None if srgb_output => self.copy_tiles(
+                    frame,
+                    nb_tiles_x,
+                    4,
+                    |tile| &tile.frame_buf,
+                    resolve_srgb_color,
+                ),
+                None => {
+                    self.copy_tiles(frame, nb_tiles_x, 4, |tile| &tile.frame_buf, resolve_color)
+                }
+            }
+        }
+        // Write back to the depth buffer, if the target has one.
+        if let Some(depth) = target.depth_buffer_mut() {
+            self.copy_tiles(depth, nb_tiles_x, 1, |tile| &tile.depth_buf, resolve_depth);
+        }
+    }
+    /// Obtains the depth of every pixel of the image, row by row.
+    pub fn depth_buffer(&self) -> Vec<f64> {
+        let mut depth = vec![f64::INFINITY; self.width * self.height];
+        let nb_tiles_x = self.width.div_ceil(self.tile_size);
+        self.copy_tiles(
+            &mut depth,
+            nb_tiles_x,
+            1,
+            |tile| &tile.depth_buf,
+            resolve_depth,
+        );
+        depth
+    }
+    /// Copies a buffer from every tile into a buffer covering the whole image.
+    ///
+    /// The samples of each pixel are resolved into a single value, even without multisampling
+    /// where they are simply converted.
+    ///
+    /// # Arguments
+    ///
+    /// * `dest` - The buffer covering the whole image.
+    /// * `nb_tiles_x` - Number of tiles in a row.
+    /// * `nb_values` - Number of values per sample in the buffers, and per pixel in `dest`.
+    /// * `tile_buffer` - Obtains the buffer to copy from a tile.
+    /// * `resolve` - Combines the values of the samples of a pixel (first argument) into the
+    ///   values of the pixel (second argument).
+    fn copy_tiles<S, D>(
+        &self,
+        dest: &mut [D],
+        nb_tiles_x: usize,
+        nb_values: usize,
+        tile_buffer: impl Fn(&Tile) -> &[S],
+        resolve: impl Fn(&[S], &mut [D]),
+    ) {
+        let tile_size = self.tile_size;
+        let (width, height) = (self.width, self.height);
+        let nb_samples = self.msaa.nb_samples();
+        for (tile_nb, tile) in self.tiles.iter().enumerate() {
+            let (tile_x, tile_y) = (tile_nb % nb_tiles_x, tile_nb / nb_tiles_x);
+            let src = tile_buffer(tile);
+
+            // Get pixel offset.
+            let first_pixel_index = tile_x * tile_size + tile_y * tile_size * width;
+            // Ensure you don't copy too far on the screen and end up wrapping.
+            let pixels_to_copy = (width - tile_x * tile_size).min(tile_size);
+
+            // For each row in the tile, copy it over to the main buffer.
+            for tile_row in 0..tile_size {
+                // Check if you are going beyond the screen's height.
+                if tile_row + tile_y * tile_size >= height {
+                    break;
+                }
+                let src_start = tile_row * tile_size * nb_values * nb_samples;
+                let dest_start = (first_pixel_index + tile_row * width) * nb_values;
+                let dest_row = &mut dest[dest_start..dest_start + pixels_to_copy * nb_values];
+                let src_row = &src[src_start..src_start + pixels_to_copy * nb_values * nb_samples];
+                for (pixel, samples) in dest_row
+                    .chunks_exact_mut(nb_values)
+                    .zip(src_row.chunks_exact(nb_values * nb_samples))
+                {
+                    resolve(samples, pixel);
+                }
             }
         }
     }
@@ -357,23 +1013,204 @@ impl Rasterizer {
     pub fn tile_size(&self) -> usize {
         self.tile_size
     }
+    /// Gets the width of the image the rasterizer draws.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    /// Gets the height of the image the rasterizer draws.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    /// Gets how the colors are tone mapped, if they are accumulated in HDR.
+    pub fn hdr(&self) -> Option<Hdr> {
+        self.hdr
+    }
+    /// Sets the fog the surfaces fade into. None removes it.
+    pub fn set_fog(&mut self, fog: Option<FrameFog>) {
+        self.fog = fog;
+    }
+    /// Sets what reflective materials reflect. None disables reflections.
+    pub fn set_environment(&mut self, environment: Option<Skybox>) {
+        self.environment = environment;
+    }
+    /// Gets whether the colors are sRGB encoded when written to a render target.
+    pub fn srgb_output(&self) -> bool {
+        self.srgb_output
+    }
+    /// Sets whether the colors are sRGB encoded when written to a render target.
+    pub fn set_srgb_output(&mut self, srgb_output: bool) {
+        self.srgb_output = srgb_output;
+    }
     // Mutable reference to the tiles.
     pub fn tiles_mut(&mut self) -> &mut [Tile] {
         &mut self.tiles
     }
 }
+/// Finds which samples of a pixel are covered by a triangle and in front of what is already drawn.
+///
+/// # Arguments
+///
+/// * `barycentric` - Barycentric coordinates (alpha, beta, gamma) at the center of the pixel.
+/// * `barycentric_grads` - Gradients (in screen space) of the alpha, beta and gamma coordinates.
+/// * `depth` - Depth of the triangle at the center of the pixel.
+/// * `depth_grad` - Gradient (in screen space) of the depth of the triangle.
+/// * `sample_offsets` - Positions of the samples relative to the center of the pixel.
+/// * `pixel_depths` - Depth already drawn at each sample of the pixel.
+///
+/// # Return
+///
+/// A mask with a bit set for each covered sample, and the depth of the triangle at each sample.
+fn covered_samples(
+    barycentric: DVec3,
+    barycentric_grads: (DVec2, DVec2, DVec2),
+    depth: f64,
+    depth_grad: DVec2,
+    sample_offsets: &[DVec2],
+    pixel_depths: &[f64],
+) -> (u8, [f64; MAX_SAMPLES]) {
+    let (alpha_grad, beta_grad, gamma_grad) = barycentric_grads;
+    let mut coverage = 0;
+    let mut sample_depths = [f64::INFINITY; MAX_SAMPLES];
+    for (sample, offset) in sample_offsets.iter().enumerate() {
+        let (alpha, beta, gamma) = (
+            barycentric.x + alpha_grad.dot(*offset),
+            barycentric.y + beta_grad.dot(*offset),
+            barycentric.z + gamma_grad.dot(*offset),
+        );
+        let sample_depth = depth + depth_grad.dot(*offset);
+        // Smaller depth means closer to screen.
+        if ((alpha >= 0.0) & (beta >= 0.0) & (gamma >= 0.0)) && sample_depth < pixel_depths[sample]
+        {
+            coverage |= 1 << sample;
+            sample_depths[sample] = sample_depth;
+        }
+    }
+    (coverage, sample_depths)
+}
+/// Combines the color of a surface with an 8 bit color already drawn behind it.
+///
+/// # Arguments
+///
+/// * `blend_mode` - How the colors are combined.
+/// * `source` - Linear color (RGBA) of the surface.
+/// * `destination` - The 8 bit color (RGBA) behind the surface.
+/// * `srgb_output` - Whether the 8 bit colors are sRGB encoded. They are then blended in linear
+///   space.
+///
+/// # Return
+///
+/// The 8 bit color (RGBA) to draw, clamped between 0-255.
+#[inline(always)]
+fn blend_8_bit(
+    blend_mode: BlendMode,
+    source: DVec4,
+    destination: U8Vec4,
+    srgb_output: bool,
+) -> U8Vec4 {
+    // Opaque surfaces do not need what is behind them.
+    let destination = if blend_mode == BlendMode::Opaque {
+        DVec4::ZERO
+    } else if srgb_output {
+        color::decode_srgb_color(destination)
+    } else {
+        destination.as_dvec4() / 255.0
+    };
+    let color = blend_mode
+        .blend(source, destination)
+        .clamp(DVec4::ZERO, DVec4::ONE);
+    if srgb_output {
+        color::encode_srgb_color(color)
+    } else {
+        (color * 255.0).as_u8vec4()
+    }
+}
+/// Averages the colors (RGBA) of the samples of a pixel.
+fn resolve_color(samples: &[u8], pixel: &mut [u8]) {
+    let nb_samples = (samples.len() / 4) as u32;
+    for (channel, value) in pixel.iter_mut().enumerate() {
+        let sum: u32 = samples
+            .iter()
+            .skip(channel)
+            .step_by(4)
+            .map(|&v| v as u32)
+            .sum();
+        *value = ((sum + nb_samples / 2) / nb_samples) as u8;
+    }
+}
+/// Averages the sRGB encoded colors (RGBA) of the samples of a pixel in linear space.
+fn resolve_srgb_color(samples: &[u8], pixel: &mut [u8]) {
+    // A single sample is already resolved.
+    if samples.len() == 4 {
+        pixel.copy_from_slice(samples);
+        return;
+    }
+    let sum: DVec4 = samples
+        .chunks_exact(4)
+        .map(|sample| color::decode_srgb_color(U8Vec4::from_slice(sample)))
+        .sum();
+    let color = sum / (samples.len() / 4) as f64;
+    pixel.copy_from_slice(&color::encode_srgb_color(color).to_array());
+}
+/// Tone maps the colors (RGBA) of the samples of a pixel, and averages them.
+///
+/// Tone mapping each sample before averaging keeps the edges of very bright surfaces smooth.
+/// The average is sRGB encoded if `srgb_output` is true.
+fn resolve_hdr_color(samples: &[f32], pixel: &mut [u8], hdr: &Hdr, srgb_output: bool) {
+    let sum: DVec4 = samples
+        .chunks_exact(4)
+        .map(|sample| {
+            let color = Vec4::from_slice(sample).as_dvec4();
+            hdr.tone_map(color.xyz()).extend(color.w.clamp(0.0, 1.0))
+        })
+        .sum();
+    let color = sum / (samples.len() / 4) as f64;
+    let color = if srgb_output {
+        color::encode_srgb_color(color)
+    } else {
+        (color * 255.0).round().as_u8vec4()
+    };
+    pixel.copy_from_slice(&color.to_array());
+}
+/// Keeps the depth of the closest sample of a pixel.
+fn resolve_depth(samples: &[f64], pixel: &mut [f64]) {
+    pixel[0] = samples.iter().copied().fold(f64::INFINITY, f64::min);
+}
+/// Perturbs the normal of a surface with the color of a tangent space normal map.
+///
+/// # Arguments
+///
+/// * `normal` - Normal of the surface (normalized).
+/// * `tangent` - Tangent of the surface (xyz) and the sign of its bitangent (w).
+/// * `map_color` - Color (RGB) of the normal map, with each channel between 0-1.
+///
+/// # Return
+///
+/// The perturbed normal (normalized), or `normal` if the tangent is zero.
+fn perturb_normal(normal: DVec3, tangent: DVec4, map_color: DVec3) -> DVec3 {
+    // Make the interpolated tangent perpendicular to the normal again.
+    let tangent_direction =
+        (tangent.xyz() - normal * normal.dot(tangent.xyz())).normalize_or_zero();
+    if tangent_direction == DVec3::ZERO {
+        return normal;
+    }
+    let bitangent = tangent.w.signum() * normal.cross(tangent_direction);
+    // Colors between 0-1 map to coordinates between -1 and 1.
+    let perturbation = map_color * 2.0 - 1.0;
+    (tangent_direction * perturbation.x + bitangent * perturbation.y + normal * perturbation.z)
+        .normalize_or_zero()
+}
 /// Pixel and depth buffer for a single tile.
+///
+/// With multisampling, the buffers hold every sample of a pixel next to each other.
 #[derive(Clone)]
 pub struct Tile {
     /// The depth buffer for a tile on the screen.
     pub depth_buf: Vec<f64>,
-    /// The frame/pixel buffer for a tile one the screen.
+    /// The frame/pixel buffer for a tile one the screen. Empty in HDR.
     pub frame_buf: Vec<u8>,
-}
-impl Tile {
-    pub fn get_buffers(&mut self) -> (&mut [u8], &mut [f64]) {
-        (&mut self.frame_buf, &mut self.depth_buf)
-    }
+    /// The linear floating point (HDR) frame/pixel buffer for a tile on the screen. Empty
+    /// unless the rasterizer is in HDR.
+    pub hdr_buf: Vec<f32>,
 }
 /// Containts the necessary data to handle a triangle from geometry binned in a tile.
 #[derive(Clone, Copy)]
@@ -1,14 +1,21 @@
 //! Contains the necessary APIs/values to shade geometry within a scene.
 
+use std::ops::{Add, Mul, Sub};
+
 use glam::DVec3;
 
-use crate::scene::light::Light;
+use crate::{
+    resources::material::Material,
+    scene::{fog::Fog, light::Light},
+};
+
+use super::shadow::ShadowMap;
 
 /// Contains the values necessary to decdie which shader to use and how to use them.
 pub struct Shader {
-    /// The ambient lighting. A value between 0-1, where 0 means no ambient light and 1 means
-    /// everything is fully illuminated.
-    pub ambient: f64,
+    /// The color of the ambient lighting. Each channel (RGB) is a value between 0-1, where 0
+    /// means no ambient light and 1 means everything is fully illuminated.
+    pub ambient: DVec3,
     /// The type of shader.
     pub shader_type: ShaderType,
 }
@@ -18,9 +25,9 @@ impl Shader {
     /// # Arguments:
     ///
     /// * `shader_type` - How the shading value is calculated.
-    /// * `ambient` - The ambient lighting. A value between 0-1, where 0 means no ambient light and 1 means
-    /// everything is fully illuminated.
-    pub fn new(ambient: f64, shader_type: ShaderType) -> Self {
+    /// * `ambient` - The color of the ambient lighting. Each channel (RGB) is a value between 0-1,
+    ///   where 0 means no ambient light and 1 means everything is fully illuminated.
+    pub fn new(ambient: DVec3, shader_type: ShaderType) -> Self {
         Shader {
             ambient,
             shader_type,
@@ -28,28 +35,277 @@ impl Shader {
     }
     /// Defines how a shader will shade a pixel.
     ///
+    /// Uses the Blinn-Phong reflection model: a diffuse (Lambert) term and a specular term based
+    /// on the half vector between the light and view directions. Each light is tinted by its
+    /// color, so the contributions of differently colored lights add up per channel.
+    ///
     /// # Arguments
     ///
     /// * `normal` - Normal of the surface the shader is currently working on (has to be normalized).
-    /// * `lights` - List of lights populating the scene.
+    /// * `position` - Position (in world space) of the surface point being shaded.
+    /// * `view_point` - Where the scene is seen from.
+    /// * `material` - Material of the surface, which dictates how its highlights look.
+    /// * `lights` - List of lights populating the scene, with their shadow maps.
     ///
     /// # Return
     ///
-    /// Value that dictates how the pixel is to be shaded.
-    /// ```
-    /// let shaded_color = color * shader.shade(...);
+    /// The light that reaches the pixel, split between the diffuse and specular reflections.
+    /// ```ignore
+    /// let lighting = shader.shade(...);
+    /// let shaded_color = color * lighting.diffuse + specular_color * lighting.specular;
     /// ```
-    pub fn shade(&self, normal: DVec3, lights: &[Light]) -> f64{
-        let mut shading: f64 = self.ambient;
-        for light in lights {
-            match light.light_type {
-                crate::scene::light::LightType::AtInfinity(dir) => {
-                    shading += light.strength * normal.dot(-dir).max(0.0);
-                },
-                crate::scene::light::LightType::Point {..} => todo!("Implement point light shading"),
+    pub fn shade(
+        &self,
+        normal: DVec3,
+        position: DVec3,
+        view_point: &ViewPoint,
+        material: &Material,
+        lights: &[FrameLight],
+    ) -> Lighting {
+        let mut lighting = Lighting {
+            diffuse: self.ambient,
+            specular: DVec3::ZERO,
+        };
+        let to_eye = view_point.direction_to_eye(position);
+        for FrameLight { light, shadow_map } in lights {
+            let (to_light, light_lighting) = shade_light(normal, position, to_eye, material, light);
+            // Reduce the intensity for surfaces in the shadow.
+            let visibility = match shadow_map {
+                Some(shadow_map) if light_lighting != Lighting::default() => {
+                    shadow_map.visibility(position, normal, to_light, light.shadow_bias)
+                }
+                _ => 1.0,
+            };
+            lighting = lighting + light_lighting * visibility;
+        }
+        lighting
+    }
+    /// Defines how a shader will shade a point, leaving the shadows out.
+    ///
+    /// Used when the lighting is not evaluated at every pixel (flat and Gouraud shading). The
+    /// shadows are then applied at every pixel with [`apply_shadows`], so that they keep their
+    /// shape instead of following the triangles.
+    ///
+    /// # Arguments
+    ///
+    /// * `normal` - Normal of the surface the shader is currently working on (has to be normalized).
+    /// * `position` - Position (in world space) of the surface point being shaded.
+    /// * `view_point` - Where the scene is seen from.
+    /// * `material` - Material of the surface, which dictates how its highlights look.
+    /// * `lights` - List of lights populating the scene, with their shadow maps.
+    /// * `shadowed` - Receives the light reaching the point from each light with a shadow map, in
+    ///   the order of `lights`.
+    ///
+    /// # Return
+    ///
+    /// The light that would reach the point if nothing were in the shadow.
+    pub fn shade_without_shadows(
+        &self,
+        normal: DVec3,
+        position: DVec3,
+        view_point: &ViewPoint,
+        material: &Material,
+        lights: &[FrameLight],
+        shadowed: &mut Vec<Lighting>,
+    ) -> Lighting {
+        let mut lighting = Lighting {
+            diffuse: self.ambient,
+            specular: DVec3::ZERO,
+        };
+        let to_eye = view_point.direction_to_eye(position);
+        for FrameLight { light, shadow_map } in lights {
+            let (_, light_lighting) = shade_light(normal, position, to_eye, material, light);
+            if shadow_map.is_some() {
+                shadowed.push(light_lighting);
+            }
+            lighting = lighting + light_lighting;
+        }
+        lighting
+    }
+}
+/// Obtains the light reaching a point from a single light, ignoring the shadows.
+///
+/// # Arguments
+///
+/// * `normal` - Normal of the surface at the point (normalized).
+/// * `position` - Position (in world space) of the point.
+/// * `to_eye` - Direction from the point to the viewer (normalized).
+/// * `material` - Material of the surface, which dictates how its highlights look.
+/// * `light` - The light, in world space.
+///
+/// # Return
+///
+/// The direction from the point to the light, and the light reaching the point.
+fn shade_light(
+    normal: DVec3,
+    position: DVec3,
+    to_eye: DVec3,
+    material: &Material,
+    light: &Light,
+) -> (DVec3, Lighting) {
+    // Direction from the surface to the light, and the light's intensity at the surface.
+    let (to_light, intensity) = light.illumination(position);
+    let diffuse = normal.dot(to_light).max(0.0);
+    // Surfaces facing away from the light get no highlights.
+    let specular = if diffuse > 0.0 {
+        let half_vector = (to_light + to_eye).normalize_or_zero();
+        normal.dot(half_vector).max(0.0).powf(material.shininess)
+    } else {
+        0.0
+    };
+    let light_color = light.normalized_color() * intensity;
+    let lighting = Lighting {
+        diffuse: light_color * diffuse,
+        specular: light_color * material.specular_strength * specular,
+    };
+    (to_light, lighting)
+}
+/// Removes the light blocked by the shadows from the lighting of a point.
+///
+/// # Arguments
+///
+/// * `lighting` - The light that would reach the point if nothing were in the shadow (see
+///   [`Shader::shade_without_shadows`]).
+/// * `shadowed` - The light reaching the point from each light with a shadow map, in the order of
+///   `lights`.
+/// * `normal` - Normal of the surface at the point (normalized).
+/// * `position` - Position (in world space) of the point.
+/// * `lights` - List of lights populating the scene, with their shadow maps.
+///
+/// # Return
+///
+/// The light that reaches the point.
+pub fn apply_shadows(
+    lighting: Lighting,
+    shadowed: impl IntoIterator<Item = Lighting>,
+    normal: DVec3,
+    position: DVec3,
+    lights: &[FrameLight],
+) -> Lighting {
+    let shadow_maps = lights
+        .iter()
+        .filter_map(|FrameLight { light, shadow_map }| {
+            shadow_map.as_ref().map(|shadow_map| (light, shadow_map))
+        });
+    let mut lighting = lighting;
+    for ((light, shadow_map), light_lighting) in shadow_maps.zip(shadowed) {
+        if light_lighting == Lighting::default() {
+            continue;
+        }
+        let (to_light, _) = light.illumination(position);
+        let visibility = shadow_map.visibility(position, normal, to_light, light.shadow_bias);
+        lighting = lighting - light_lighting * (1.0 - visibility);
+    }
+    lighting
+}
+/// Obtains how much light a surface reflects, with Schlick's approximation of the Fresnel
+/// equations. Surfaces reflect more when seen at grazing angles.
+///
+/// # Arguments
+///
+/// * `reflectivity` - How much light the surface reflects when seen head on. A value between 0-1.
+/// * `cos_theta` - Cosine of the angle between the normal of the surface and the direction to
+///   the viewer.
+///
+/// # Return
+///
+/// A value between `reflectivity` (head on) and 1 (at a grazing angle).
+pub fn fresnel(reflectivity: f64, cos_theta: f64) -> f64 {
+    let reflectivity = reflectivity.clamp(0.0, 1.0);
+    reflectivity + (1.0 - reflectivity) * (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+}
+/// A light as it is when shading a frame.
+pub struct FrameLight {
+    /// The light, in world space.
+    pub light: Light,
+    /// Depth of the scene as seen from the light, if it casts shadows.
+    pub shadow_map: Option<ShadowMap>,
+}
+/// The fog as it is when shading a frame.
+#[derive(Clone, Copy)]
+pub struct FrameFog {
+    /// The fog of the scene.
+    pub fog: Fog,
+    /// Linear color (RGB) of the fog, with each channel between 0-1.
+    pub color: DVec3,
+    /// Position (in world space) of the camera.
+    pub camera_position: DVec3,
+}
+impl FrameFog {
+    /// Fades a color into the fog.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - Linear color (RGB) of the surface.
+    /// * `position` - Position (in world space) of the surface point.
+    /// * `w_inv` - Inverse of the clip space w of the point. With a perspective projection, w is
+    ///   the depth of the point in view space.
+    /// * `view_point` - Where the scene is seen from.
+    pub fn apply(
+        &self,
+        color: DVec3,
+        position: DVec3,
+        w_inv: f64,
+        view_point: &ViewPoint,
+    ) -> DVec3 {
+        let distance = match view_point {
+            ViewPoint::Position(_) => 1.0 / w_inv,
+            // w is always 1 with an orthographic projection.
+            ViewPoint::Direction(direction) => {
+                (position - self.camera_position).dot(direction.normalize_or_zero())
             }
+        };
+        color.lerp(self.color, self.fog.amount(distance, position.y))
+    }
+}
+/// The light reaching a point of a surface. Each channel (RGB) is a value of 0 or more, which
+/// goes above 1 when bright lights add up.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Lighting {
+    /// Light reflected diffusely, ambient light included. Multiplies the color of the surface.
+    pub diffuse: DVec3,
+    /// Light reflected by the specular highlights. Multiplies the specular color of the surface.
+    pub specular: DVec3,
+}
+impl Lighting {
+    /// Linearly interpolates between two lightings.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The lighting obtained when `t` is 1.
+    /// * `t` - Interpolation value. 0 gives `self`, 1 gives `other`.
+    pub fn lerp(self, other: Lighting, t: f64) -> Lighting {
+        Lighting {
+            diffuse: self.diffuse.lerp(other.diffuse, t),
+            specular: self.specular.lerp(other.specular, t),
+        }
+    }
+}
+impl Add for Lighting {
+    type Output = Lighting;
+    fn add(self, other: Lighting) -> Lighting {
+        Lighting {
+            diffuse: self.diffuse + other.diffuse,
+            specular: self.specular + other.specular,
+        }
+    }
+}
+impl Sub for Lighting {
+    type Output = Lighting;
+    fn sub(self, other: Lighting) -> Lighting {
+        Lighting {
+            diffuse: self.diffuse - other.diffuse,
+            specular: self.specular - other.specular,
+        }
+    }
+}
+impl Mul<f64> for Lighting {
+    type Output = Lighting;
+    fn mul(self, factor: f64) -> Lighting {
+        Lighting {
+            diffuse: self.diffuse * factor,
+            specular: self.specular * factor,
         }
-        shading.min(1.0)
     }
 }
 /// The different possible types of shaders.
@@ -61,3 +317,30 @@ pub enum ShaderType {
     /// Single shading value per geometry face.
     Flat,
 }
+/// Where the scene is seen from. Used to find the direction of the viewer when computing specular
+/// highlights.
+#[derive(Clone, Copy)]
+pub enum ViewPoint {
+    /// The viewer is at a position in world space (perspective projection).
+    Position(DVec3),
+    /// Every view ray has the same direction in world space (orthographic projection).
+    Direction(DVec3),
+}
+impl ViewPoint {
+    /// Obtains the normalized direction from a point in world space to the viewer.
+    pub fn direction_to_eye(&self, position: DVec3) -> DVec3 {
+        match self {
+            ViewPoint::Position(eye) => (*eye - position).normalize_or_zero(),
+            ViewPoint::Direction(direction) => -direction.normalize_or_zero(),
+        }
+    }
+    /// Obtains how far a point in world space is from the viewer, along the view rays.
+    ///
+    /// Only useful to compare points with each other: bigger values are further away.
+    pub fn distance(&self, position: DVec3) -> f64 {
+        match self {
+            ViewPoint::Position(eye) => (position - *eye).length(),
+            ViewPoint::Direction(direction) => position.dot(direction.normalize_or_zero()),
+        }
+    }
+}
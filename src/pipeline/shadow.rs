@@ -0,0 +1,159 @@
+//! Contains everything needed to find which parts of the scene the lights cannot reach.
+
+use glam::{DMat4, DVec3};
+
+/// Direction each face of a cube shadow map looks towards, along with its up direction. In the
+/// order +X, -X, +Y, -Y, +Z, -Z.
+pub const CUBE_FACES: [(DVec3, DVec3); 6] = [
+    (DVec3::X, DVec3::NEG_Y),
+    (DVec3::NEG_X, DVec3::NEG_Y),
+    (DVec3::Y, DVec3::Z),
+    (DVec3::NEG_Y, DVec3::NEG_Z),
+    (DVec3::Z, DVec3::NEG_Y),
+    (DVec3::NEG_Z, DVec3::NEG_Y),
+];
+/// Depth of the scene as seen from a light. Anything further from the light than what the map
+/// holds is in the shadow.
+pub enum ShadowMap {
+    /// The scene seen through a single orthographic projection, for lights of type `AtInfinity`.
+    Directional {
+        /// The depth of the scene.
+        face: ShadowFace,
+        /// Size (in world units) of a pixel of the map.
+        texel_size: f64,
+    },
+    /// The scene seen through the six faces of a cube centered on the light, each with a 90°
+    /// perspective projection, for lights of type `Point`.
+    Cube {
+        /// Position (in world space) of the light.
+        position: DVec3,
+        /// The depth of the scene through each face, in the order +X, -X, +Y, -Y, +Z, -Z.
+        faces: Box<[ShadowFace; 6]>,
+        /// Size (in world units) of a pixel of the faces, at a distance of 1 from the light.
+        texel_size: f64,
+    },
+}
+impl ShadowMap {
+    /// Obtains how much of a point is reached by the light.
+    ///
+    /// The point is first pushed off its surface by about a pixel of the map along the normal,
+    /// and by `bias` pixels towards the light, so that surfaces do not shadow themselves. The 3x3
+    /// pixels around the point are then compared (percentage closer filtering) to soften the
+    /// edges of the shadows.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position (in world space) of the point.
+    /// * `normal` - Normal of the surface at the point (normalized).
+    /// * `to_light` - Direction from the point to the light (normalized).
+    /// * `bias` - Distance (in pixels of the map) the point is pushed towards the light.
+    ///
+    /// # Return
+    ///
+    /// A value between 0-1, where 0 means the point is fully in the shadow.
+    pub fn visibility(&self, position: DVec3, normal: DVec3, to_light: DVec3, bias: f64) -> f64 {
+        match self {
+            ShadowMap::Directional { face, texel_size } => {
+                face.visibility(position + (normal + to_light * bias) * *texel_size)
+            }
+            ShadowMap::Cube {
+                position: light_position,
+                faces,
+                texel_size,
+            } => {
+                // The pixels of the faces get bigger further away from the light.
+                let from_light = position - *light_position;
+                let texel_size = texel_size * from_light.length();
+                let position = position + (normal + to_light * bias) * texel_size;
+                faces[cube_face(position - *light_position)].visibility(position)
+            }
+        }
+    }
+}
+/// Depth of the scene as seen through a single projection.
+pub struct ShadowFace {
+    /// Transform from world space to the screen space of the face, where x and y are in pixels
+    /// and z is the depth.
+    world_to_map: DMat4,
+    /// Number of pixels along each side of the (square) face.
+    size: usize,
+    /// Depth of the closest surface for each pixel of the face, row by row.
+    depth: Vec<f64>,
+}
+impl ShadowFace {
+    /// Creates a shadow face.
+    ///
+    /// # Arguments
+    ///
+    /// * `world_to_map` - Transform from world space to the screen space of the face.
+    /// * `size` - Number of pixels along each side of the face.
+    /// * `depth` - Depth of the closest surface for each pixel of the face, row by row.
+    pub fn new(world_to_map: DMat4, size: usize, depth: Vec<f64>) -> Self {
+        ShadowFace {
+            world_to_map,
+            size,
+            depth,
+        }
+    }
+    /// Obtains how much of a point is reached by the light, by comparing its depth with the 3x3
+    /// pixels around it.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position (in world space) of the point, already offset from its surface.
+    ///
+    /// # Return
+    ///
+    /// A value between 0-1, where 0 means the point is fully in the shadow. Points outside of the
+    /// face are fully lit.
+    fn visibility(&self, position: DVec3) -> f64 {
+        let map_position = self.world_to_map.project_point3(position);
+        let (x, y) = (map_position.x.floor() as i64, map_position.y.floor() as i64);
+        let size = self.size as i64;
+        // Nothing casts shadows outside of the face.
+        if !(0..size).contains(&x) || !(0..size).contains(&y) {
+            return 1.0;
+        }
+        let mut lit = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                // Pixels around the edges are reused for the samples beyond them.
+                let sample_x = (x + dx).clamp(0, size - 1);
+                let sample_y = (y + dy).clamp(0, size - 1);
+                if map_position.z <= self.depth[(sample_x + sample_y * size) as usize] {
+                    lit += 1;
+                }
+            }
+        }
+        lit as f64 / 9.0
+    }
+}
+/// Obtains the face of a cube that a direction from its center goes through.
+///
+/// # Arguments
+///
+/// * `direction` - The direction, which does not need to be normalized.
+///
+/// # Return
+///
+/// The index of the face in [`CUBE_FACES`].
+fn cube_face(direction: DVec3) -> usize {
+    let abs = direction.abs();
+    if abs.x >= abs.y && abs.x >= abs.z {
+        if direction.x >= 0.0 {
+            0
+        } else {
+            1
+        }
+    } else if abs.y >= abs.z {
+        if direction.y >= 0.0 {
+            2
+        } else {
+            3
+        }
+    } else if direction.z >= 0.0 {
+        4
+    } else {
+        5
+    }
+}
@@ -46,3 +46,35 @@ pub fn perspective_transform(near_clip: f32, far_clip: f32, aspect_ratio: f32, h
         DVec4::new(0.0, 0.0, -(2.0 * f * n) / (f - n), 0.0),
     )
 }
+/// Obtain the orthographic projection matrix given a camera's view box.
+///
+/// The view box is centered on the camera's forward axis.
+///
+/// # Arguments
+///
+/// * `near_clip` - The near clipping plane of the view box.
+/// * `far_clip` - The far clipping plane of the view box.
+/// * `width` - Width of the view box.
+/// * `height` - Height of the view box.
+pub fn orthographic_transform(near_clip: f32, far_clip: f32, width: f32, height: f32) -> DMat4 {
+    // Get the left, right, top and bottom planes of the view box (-l=r and -b=t).
+    let r = width as f64 / 2.0;
+    let l = -r;
+    let t = height as f64 / 2.0;
+    let b = -t;
+    // Convert near and far clipping planes to f64.
+    let n = near_clip as f64;
+    let f = far_clip as f64;
+
+    DMat4::from_cols(
+        DVec4::new(2.0 / (r - l), 0.0, 0.0, 0.0),
+        DVec4::new(0.0, 2.0 / (t - b), 0.0, 0.0),
+        DVec4::new(0.0, 0.0, -2.0 / (f - n), 0.0),
+        DVec4::new(
+            -(r + l) / (r - l),
+            -(t + b) / (t - b),
+            -(f + n) / (f - n),
+            1.0,
+        ),
+    )
+}
@@ -61,9 +61,9 @@ impl TextureLoader {
                     }
                 }
                 // Finally, create the texture.
-                Texture::from_pixels(width, height, &pixels, Format::RGB24).unwrap_or_else(|e| {
+                Texture::from_pixels(width, height, &pixels, Format::SRGB24).unwrap_or_else(|e| {
                     eprintln!("Could not create texture: {e}");
-                    Texture::new(width, height, Format::RGB24)
+                    Texture::new(width, height, Format::SRGB24)
                 })
             }
         }
@@ -116,9 +116,9 @@ impl MeshLoader {
     pub fn new() -> Self {
         MeshLoader { scale: 1.0 }
     }
-    /// Loads a default mesh.
-    pub fn load_default_mesh(&self, mesh: DefaultMesh, texture_id: Option<u32>) -> Mesh {
-        match mesh {
+    /// Loads a default mesh. Its tangents are generated (see [`Mesh::generate_tangents`]).
+    pub fn load_default_mesh(&self, mesh: DefaultMesh, material_id: Option<u32>) -> Mesh {
+        let mut loaded_mesh = match mesh {
             DefaultMesh::Cube {
                 size,
                 u_repeat,
@@ -144,39 +144,48 @@ impl MeshLoader {
                     DVec2::new(0.0, 1.0 * v_repeat),
                     DVec2::new(1.0 * u_repeat, 1.0 * v_repeat),
                 ];
+                // Normal of each side (-X, +X, -Y, +Y, -Z, +Z).
+                let normals = [
+                    DVec3::NEG_X,
+                    DVec3::X,
+                    DVec3::NEG_Y,
+                    DVec3::Y,
+                    DVec3::NEG_Z,
+                    DVec3::Z,
+                ];
                 // Contains the indices of the triangle making up the mesh.
                 let mut triangles = Vec::<usize>::with_capacity(24);
                 // Fill in the vertices for each side.
                 // -X
-                vertices.push(Vertex::new(corners[4], uvs[0])); //0
-                vertices.push(Vertex::new(corners[0], uvs[1])); //1
-                vertices.push(Vertex::new(corners[3], uvs[3])); //2
-                vertices.push(Vertex::new(corners[7], uvs[2])); //3
+                vertices.push(Vertex::new(corners[4], uvs[0], normals[0])); //0
+                vertices.push(Vertex::new(corners[0], uvs[1], normals[0])); //1
+                vertices.push(Vertex::new(corners[3], uvs[3], normals[0])); //2
+                vertices.push(Vertex::new(corners[7], uvs[2], normals[0])); //3
                                                                 // +X
-                vertices.push(Vertex::new(corners[1], uvs[0])); //4
-                vertices.push(Vertex::new(corners[5], uvs[1])); //5
-                vertices.push(Vertex::new(corners[6], uvs[3])); //6
-                vertices.push(Vertex::new(corners[2], uvs[2])); //7
+                vertices.push(Vertex::new(corners[1], uvs[0], normals[1])); //4
+                vertices.push(Vertex::new(corners[5], uvs[1], normals[1])); //5
+                vertices.push(Vertex::new(corners[6], uvs[3], normals[1])); //6
+                vertices.push(Vertex::new(corners[2], uvs[2], normals[1])); //7
                                                                 // -Y
-                vertices.push(Vertex::new(corners[0], uvs[0])); //8
-                vertices.push(Vertex::new(corners[1], uvs[1])); //9
-                vertices.push(Vertex::new(corners[2], uvs[3])); //10
-                vertices.push(Vertex::new(corners[3], uvs[2])); //11
+                vertices.push(Vertex::new(corners[0], uvs[0], normals[2])); //8
+                vertices.push(Vertex::new(corners[1], uvs[1], normals[2])); //9
+                vertices.push(Vertex::new(corners[2], uvs[3], normals[2])); //10
+                vertices.push(Vertex::new(corners[3], uvs[2], normals[2])); //11
                                                                 // +Y
-                vertices.push(Vertex::new(corners[5], uvs[0])); //12
-                vertices.push(Vertex::new(corners[4], uvs[1])); //13
-                vertices.push(Vertex::new(corners[7], uvs[3])); //14
-                vertices.push(Vertex::new(corners[6], uvs[2])); //15
+                vertices.push(Vertex::new(corners[5], uvs[0], normals[3])); //12
+                vertices.push(Vertex::new(corners[4], uvs[1], normals[3])); //13
+                vertices.push(Vertex::new(corners[7], uvs[3], normals[3])); //14
+                vertices.push(Vertex::new(corners[6], uvs[2], normals[3])); //15
                                                                 // -Z
-                vertices.push(Vertex::new(corners[4], uvs[0])); //16
-                vertices.push(Vertex::new(corners[5], uvs[1])); //17
-                vertices.push(Vertex::new(corners[1], uvs[3])); //18
-                vertices.push(Vertex::new(corners[0], uvs[2])); //19
+                vertices.push(Vertex::new(corners[4], uvs[0], normals[4])); //16
+                vertices.push(Vertex::new(corners[5], uvs[1], normals[4])); //17
+                vertices.push(Vertex::new(corners[1], uvs[3], normals[4])); //18
+                vertices.push(Vertex::new(corners[0], uvs[2], normals[4])); //19
                                                                 // +Z//0
-                vertices.push(Vertex::new(corners[3], uvs[0])); //20
-                vertices.push(Vertex::new(corners[2], uvs[1])); //21
-                vertices.push(Vertex::new(corners[6], uvs[3])); //22
-                vertices.push(Vertex::new(corners[7], uvs[2])); //23
+                vertices.push(Vertex::new(corners[3], uvs[0], normals[5])); //20
+                vertices.push(Vertex::new(corners[2], uvs[1], normals[5])); //21
+                vertices.push(Vertex::new(corners[6], uvs[3], normals[5])); //22
+                vertices.push(Vertex::new(corners[7], uvs[2], normals[5])); //23
 
                 // Fill up triangles indices
                 for i in 0..6 {
@@ -189,7 +198,7 @@ impl MeshLoader {
                     triangles.push(4 * i + 3);
                     triangles.push(4 * i + 0);
                 }
-                Mesh::new(texture_id, vertices, triangles)
+                Mesh::new(material_id, vertices, triangles)
             }
             DefaultMesh::SingleFace(size) => {
                 let half_size = size / 2.0;
@@ -212,14 +221,16 @@ impl MeshLoader {
                     DVec2::new(0.0, 1.0),
                     DVec2::new(1.0, 1.0),
                 ];
+                // Normal of the face.
+                let normal = DVec3::Z;
                 // Contains the indices of the triangle making up the mesh.
                 let mut triangles = Vec::<usize>::with_capacity(24);
                 // Fill in the vertices for each side.
                 // +Z//0
-                vertices.push(Vertex::new(corners[3], uvs[0])); //0
-                vertices.push(Vertex::new(corners[2], uvs[1])); //1
-                vertices.push(Vertex::new(corners[6], uvs[3])); //2
-                vertices.push(Vertex::new(corners[7], uvs[2])); //3
+                vertices.push(Vertex::new(corners[3], uvs[0], normal)); //0
+                vertices.push(Vertex::new(corners[2], uvs[1], normal)); //1
+                vertices.push(Vertex::new(corners[6], uvs[3], normal)); //2
+                vertices.push(Vertex::new(corners[7], uvs[2], normal)); //3
 
                 // Fill up triangles indices
                 for i in 0..1 {
@@ -232,9 +243,11 @@ impl MeshLoader {
                     triangles.push(4 * i + 3);
                     triangles.push(4 * i + 0);
                 }
-                Mesh::new(texture_id, vertices, triangles)
+                Mesh::new(material_id, vertices, triangles)
             }
-        }
+        };
+        loaded_mesh.generate_tangents();
+        loaded_mesh
     }
     /// Loads a mesh from a file.
     ///
@@ -0,0 +1,262 @@
+//! Contains everything pertaining to the materials meshes are made of.
+use core::fmt;
+use std::collections::HashMap;
+
+use glam::{DVec3, DVec4};
+
+use super::sampler::Sampler;
+
+/// Owns the materials as well as the necessary maps to efficiently access them.
+pub struct MaterialCatalog {
+    /// Id to give to the next material added. Start at 1, and use 0 for issues.
+    next_id: u32,
+    /// Map containing the id of each material.
+    materials: HashMap<u32, Material>,
+    /// Given a material name, obtain the id of the material.
+    material_ids: HashMap<String, u32>,
+}
+impl MaterialCatalog {
+    /// Creates a default material catalog with no materials.
+    pub fn new() -> Self {
+        MaterialCatalog {
+            next_id: 1,
+            materials: HashMap::new(),
+            material_ids: HashMap::new(),
+        }
+    }
+    /// Add a material to the catalog.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the material.
+    /// * `material` - The material to add to the catalog.
+    ///
+    /// # Return
+    ///
+    /// The id of the material that was added to the catalog if succesful.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`MaterialError::MaterialNameAlreadyExists`] if a material with the same name is
+    /// already in the catalog.
+    pub fn add_material(&mut self, name: String, material: Material) -> Result<u32, MaterialError> {
+        if let Some(&id) = self.material_ids.get(&name) {
+            return Err(MaterialError::MaterialNameAlreadyExists { name, id });
+        }
+        self.material_ids.insert(name, self.next_id);
+        self.materials.insert(self.next_id, material);
+        self.next_id += 1;
+        Ok(self.next_id - 1)
+    }
+    /// Get the material from its id
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Id of the material.
+    ///
+    /// # Return
+    ///
+    /// A reference to the material associated with the `id`. None otherwise.
+    pub fn material_from_id(&self, id: u32) -> Option<&Material> {
+        self.materials.get(&id)
+    }
+    /// Get a mutable reference to the material from its id
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Id of the material.
+    ///
+    /// # Return
+    ///
+    /// A mutable reference to the material associated with the `id`. None otherwise.
+    pub fn material_from_id_mut(&mut self, id: u32) -> Option<&mut Material> {
+        self.materials.get_mut(&id)
+    }
+    /// Get the id of a material given its name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the material.
+    ///
+    /// # Return
+    ///
+    /// The id associated with the `name`, if it exists. None otherwise.
+    pub fn id_from_name(&self, name: &str) -> Option<u32> {
+        self.material_ids.get(name).copied()
+    }
+}
+impl Default for MaterialCatalog {
+    fn default() -> Self {
+        MaterialCatalog::new()
+    }
+}
+// Getters and setters.
+impl MaterialCatalog {
+    /// Obtains a reference for the map of materials.
+    pub fn materials(&self) -> &HashMap<u32, Material> {
+        &self.materials
+    }
+    /// Obtains a reference for the map of material ids.
+    pub fn materials_ids(&self) -> &HashMap<String, u32> {
+        &self.material_ids
+    }
+}
+////////////////////////////////////////////////////////////
+/// Defines how the surface of a mesh looks.
+///
+/// The textures are referenced through their id in the [`super::texture::TextureCatalog`].
+#[derive(Clone)]
+pub struct Material {
+    /// Color (RGBA) of the surface. Each channel is a value between 0-1. Multiplies the colors of
+    /// the vertices and of the diffuse texture, if there is one.
+    pub base_color: DVec4,
+    /// Texture giving the color of the surface.
+    pub diffuse_texture: Option<u32>,
+    /// Texture giving the color of the specular highlights.
+    pub specular_texture: Option<u32>,
+    /// Color (RGB) of the light emitted by the surface. Multiplies the color of the emissive
+    /// texture, if there is one. Emitted light does not depend on the lights of the scene.
+    pub emissive: DVec3,
+    /// Texture giving the color of the light emitted by the surface.
+    pub emissive_texture: Option<u32>,
+    /// Tangent space normal map of the surface, with its green channel pointing up. Needs the
+    /// tangents of the mesh (see [`super::mesh::Mesh::generate_tangents`]), and is only used by
+    /// per-pixel shading.
+    pub normal_texture: Option<u32>,
+    /// How every texture of the material is sampled. None uses the sampler of each texture.
+    pub sampler: Option<Sampler>,
+    /// How strong the specular highlights are compared to the diffuse lighting. 0 means no
+    /// specular highlights.
+    pub specular_strength: f64,
+    /// How concentrated the specular highlights are. Bigger values give smaller and sharper
+    /// highlights.
+    pub shininess: f64,
+    /// How much of the environment (see [`crate::scene::Scene::set_environment_map`]) the surface
+    /// reflects when seen head on, in place of its diffuse lighting. A value between 0-1, where 0
+    /// reflects nothing. Reflections get stronger at grazing angles (Fresnel effect).
+    pub reflectivity: f64,
+    /// How opaque the surface is. A value between 0-1, where 0 is fully transparent.
+    pub opacity: f64,
+    /// Pixels whose alpha (from the base color, the vertices and the diffuse texture) is below
+    /// this value are discarded, leaving holes in the surface. Unlike blending, this does not
+    /// depend on the order surfaces are drawn in. None keeps every pixel.
+    pub alpha_cutoff: Option<f64>,
+    /// Whether both sides of the triangles are drawn. When false, triangles facing away from the
+    /// camera are culled.
+    pub double_sided: bool,
+    /// How the surface is combined with what is already drawn behind it. Meshes with a
+    /// transparent blend mode are drawn after the opaque ones, from back to front, and do not
+    /// hide what is drawn after them.
+    pub blend_mode: BlendMode,
+}
+impl Material {
+    /// Creates a white, opaque and single sided material with no textures.
+    ///
+    /// The specular highlights default to a strength of 0.5 and a shininess of 32.
+    pub fn new() -> Self {
+        Material {
+            base_color: DVec4::ONE,
+            diffuse_texture: None,
+            specular_texture: None,
+            emissive: DVec3::ZERO,
+            emissive_texture: None,
+            normal_texture: None,
+            sampler: None,
+            specular_strength: 0.5,
+            shininess: 32.0,
+            reflectivity: 0.0,
+            opacity: 1.0,
+            alpha_cutoff: None,
+            double_sided: false,
+            blend_mode: BlendMode::Opaque,
+        }
+    }
+    /// Creates a default material (see [`Material::new`]) whose color is given by a texture.
+    ///
+    /// # Arguments
+    ///
+    /// * `texture_id` - The id of the diffuse texture.
+    pub fn from_texture(texture_id: u32) -> Self {
+        Material {
+            diffuse_texture: Some(texture_id),
+            ..Material::new()
+        }
+    }
+}
+impl Default for Material {
+    fn default() -> Self {
+        Material::new()
+    }
+}
+/// How the color of a surface is combined with the color already drawn behind it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BlendMode {
+    /// The surface replaces what is behind it.
+    Opaque,
+    /// The surface is mixed with what is behind it according to its alpha.
+    Alpha,
+    /// The surface's color, weighted by its alpha, is added to what is behind it. Useful for
+    /// glowing effects.
+    Additive,
+    /// What is behind the surface is tinted by the surface's color, weighted by its alpha.
+    Multiply,
+}
+impl BlendMode {
+    /// Whether surfaces using this blend mode let what is behind them show through.
+    pub fn is_transparent(&self) -> bool {
+        *self != BlendMode::Opaque
+    }
+    /// Combines the color of a surface with the color behind it.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Color (RGBA) of the surface being drawn. Each channel is a value of 0 or more,
+    ///   with alpha between 0-1.
+    /// * `destination` - Color (RGBA) already drawn behind the surface. Each channel is a value
+    ///   of 0 or more, with alpha between 0-1.
+    ///
+    /// # Return
+    ///
+    /// The resulting color (RGBA). Its color channels are not clamped, and can go above 1 (with
+    /// additive blending for example).
+    pub fn blend(&self, source: DVec4, destination: DVec4) -> DVec4 {
+        let alpha = source.w;
+        let (src, dst) = (source.truncate(), destination.truncate());
+        let blended = match self {
+            BlendMode::Opaque => return source,
+            BlendMode::Alpha => {
+                let color = src * alpha + dst * (1.0 - alpha);
+                return color.extend(alpha + destination.w * (1.0 - alpha));
+            }
+            BlendMode::Additive => dst + src * alpha,
+            BlendMode::Multiply => dst * DVec3::ONE.lerp(src, alpha),
+        };
+        blended.extend(destination.w)
+    }
+}
+/// List of error that can be thrown when using materials.
+#[derive(Debug)]
+pub enum MaterialError {
+    /// Used when the user tries to add a material that already exists in the catalog.
+    MaterialNameAlreadyExists {
+        /// Name of the material.
+        name: String,
+        /// Id of the existing material.
+        id: u32,
+    },
+}
+
+impl fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaterialError::MaterialNameAlreadyExists { name, id } => {
+                write!(
+                    f,
+                    "The material with name '{}' already exists with id '{}'.",
+                    name, id
+                )
+            }
+        }
+    }
+}
+impl std::error::Error for MaterialError {}
@@ -1,11 +1,11 @@
 //! Handles everything related to triangle meshes.
-use glam::{DMat4, DQuat, DVec2, DVec3, DVec4};
+use glam::{DMat4, DQuat, DVec2, DVec3, DVec4, Vec4Swizzles};
 
 /// Contains everything required to render a triangle mesh.
 #[derive(Clone)]
 pub struct Mesh {
-    /// The id of the texture which is owned by the [`super::texture::TextureCatalog`].
-    texture_id: Option<u32>,
+    /// The id of the material which is owned by the [`super::material::MaterialCatalog`].
+    material_id: Option<u32>,
     /// Vector defining the mesh's translation.
     translation: DVec3,
     /// Vector defining scaling. (x_scale, y_scale, z_scale)
@@ -19,15 +19,16 @@ pub struct Mesh {
     /// The list of indices that define the triangles in the mesh. Each successive 3 idex represent
     /// a triangle.
     triangles: Vec<usize>,
+    /// Whether the mesh casts shadows from the lights that can cast them.
+    casts_shadows: bool,
 }
 impl Mesh {
     /// Creates a new [`Mesh`].
     ///
     /// # Arguments
     ///
-    /// * `texture_id` - The id of the texture to use, if any.
-    /// No texture defaults to a black texture.
-    /// object.
+    /// * `material_id` - The id of the material to use, if any.
+    /// No material defaults to a white material (see [`super::material::Material::new`]).
     /// * `vertices` - The local vertices making up the mesh.
     /// * `triangles` - The indices representing the triangle within the mesh. (The triangles are
     /// defined CCW when looked at from the exterior)
@@ -37,15 +38,16 @@ impl Mesh {
     /// No verifications are made to ensure validity of the uv
     /// coordinates, position of the vertices and indices of the triangles.
     /// It is up to the user to ensure it.
-    pub fn new(texture_id: Option<u32>, vertices: Vec<Vertex>, triangles: Vec<usize>) -> Self {
+    pub fn new(material_id: Option<u32>, vertices: Vec<Vertex>, triangles: Vec<usize>) -> Self {
         Mesh {
-            texture_id,
+            material_id,
             world_transfrom: DMat4::IDENTITY,
             translation: DVec3::ZERO,
             quat: DQuat::IDENTITY,
             scale: DVec3::new(1.0, 1.0, 1.0),
             local_vertices: vertices,
             triangles,
+            casts_shadows: true,
         }
     }
     /// Given a transformation matrix, apply it to the [`Mesh`].
@@ -78,6 +80,74 @@ impl Mesh {
         self.scale += scale;
         self.update_transform();
     }
+    /// Generates the tangent of every vertex from the positions, UV coordinates and normals.
+    ///
+    /// The tangents are weighted like MikkTSpace: at each corner of a triangle, the triangle's
+    /// tangent and bitangent are made perpendicular to the vertex's normal, normalized and
+    /// weighted by the angle of the corner, so that neither the size of the triangles nor how
+    /// finely they are split changes the result. The sum is then made perpendicular to the normal
+    /// again (Gram-Schmidt). Unlike MikkTSpace, vertices shared by triangles with mirrored UV
+    /// coordinates are not split, so the tangents of such seams are averaged.
+    ///
+    /// Like MikkTSpace, the bitangent is not stored: the `w` of the tangent holds its sign and it
+    /// is rebuilt when shading as `w * normal.cross(tangent)`. The bitangent points towards the
+    /// top of the texture (decreasing v), so normal maps are expected with their green channel
+    /// pointing up.
+    ///
+    /// Vertices whose triangles have degenerate UV coordinates get a zero tangent, which disables
+    /// normal mapping for them.
+    pub fn generate_tangents(&mut self) {
+        let mut tangents = vec![DVec3::ZERO; self.local_vertices.len()];
+        let mut bitangents = vec![DVec3::ZERO; self.local_vertices.len()];
+        for triangle in self.triangles.chunks_exact(3) {
+            let (a, b, c) = (
+                &self.local_vertices[triangle[0]],
+                &self.local_vertices[triangle[1]],
+                &self.local_vertices[triangle[2]],
+            );
+            // Edges of the triangle in space and in texture space.
+            let (edge_1, edge_2) = (
+                b.position.xyz() - a.position.xyz(),
+                c.position.xyz() - a.position.xyz(),
+            );
+            let (duv_1, duv_2) = (b.uv - a.uv, c.uv - a.uv);
+            let determinant = duv_1.x * duv_2.y - duv_2.x * duv_1.y;
+            if determinant.abs() < f64::EPSILON {
+                continue;
+            }
+            // Directions in space along which u and v increase.
+            let tangent = (edge_1 * duv_2.y - edge_2 * duv_1.y) / determinant;
+            let bitangent = (edge_2 * duv_1.x - edge_1 * duv_2.x) / determinant;
+            let positions = [a, b, c].map(|vertex| vertex.position.xyz());
+            let normals = [a, b, c].map(|vertex| vertex.normal);
+            for (corner, &index) in triangle.iter().enumerate() {
+                let normal = normals[corner];
+                let project = |v: DVec3| (v - normal * normal.dot(v)).normalize_or_zero();
+                // Angle of the corner, measured in the plane perpendicular to the normal.
+                let position = positions[corner];
+                let angle = project(positions[(corner + 1) % 3] - position)
+                    .dot(project(positions[(corner + 2) % 3] - position))
+                    .clamp(-1.0, 1.0)
+                    .acos();
+                tangents[index] += project(tangent) * angle;
+                bitangents[index] += project(bitangent) * angle;
+            }
+        }
+        for ((vertex, tangent), bitangent) in
+            self.local_vertices.iter_mut().zip(tangents).zip(bitangents)
+        {
+            let normal = vertex.normal;
+            // Gram-Schmidt to make the tangent perpendicular to the normal.
+            let tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+            // The bitangent points towards decreasing v.
+            let handedness = if normal.cross(tangent).dot(-bitangent) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            vertex.tangent = tangent.extend(handedness);
+        }
+    }
     /// Update the transform of the mesh to keep it synchronized with its
     /// translation, rotation and scale.
     fn update_transform(&mut self) {
@@ -86,17 +156,25 @@ impl Mesh {
 }
 // Getters and setters
 impl Mesh {
-    /// Set a new texture for the mesh.
+    /// Set a new material for the mesh.
     ///
     /// # Arguments
     ///
-    /// * `texture_id` - New texture for the mesh.
-    pub fn set_texture(&mut self, texture_id: Option<u32>) {
-        self.texture_id = texture_id;
+    /// * `material_id` - New material for the mesh.
+    pub fn set_material(&mut self, material_id: Option<u32>) {
+        self.material_id = material_id;
+    }
+    /// Gets the material id if there is one.
+    pub fn material_id(&self) -> Option<u32> {
+        self.material_id
+    }
+    /// Sets whether the mesh casts shadows.
+    pub fn set_casts_shadows(&mut self, casts_shadows: bool) {
+        self.casts_shadows = casts_shadows;
     }
-    /// Gets the texture id if there is one.
-    pub fn texture_id(&self) -> Option<u32> {
-        self.texture_id
+    /// Gets whether the mesh casts shadows.
+    pub fn casts_shadows(&self) -> bool {
+        self.casts_shadows
     }
     /// Exposes a reference to the list of vertices making up the mesh.
     pub fn vertices(&self) -> &Vec<Vertex> {
@@ -152,18 +230,29 @@ pub struct Vertex {
     position: DVec4,
     /// UV coordinates of the vertex.
     uv: DVec2,
+    /// Normal of the surface at the vertex (normalized).
+    normal: DVec3,
+    /// Color (RGBA) of the vertex. Each channel is a value between 0-1.
+    color: DVec4,
+    /// Tangent of the surface at the vertex (xyz), and the sign of its bitangent (w). Zero until
+    /// [`Mesh::generate_tangents`] is called.
+    tangent: DVec4,
 }
 impl Vertex {
-    /// Constructs a new Vertex.
+    /// Constructs a new white Vertex.
     ///
     /// # Arguments
     ///
     /// * `position` - The position in space of the vector.
     /// * `uv` - The UV coordinates of the vertex.
-    pub fn new(position: DVec3, uv: DVec2) -> Self {
+    /// * `normal` - The normal of the surface at the vertex. It will be normalized.
+    pub fn new(position: DVec3, uv: DVec2, normal: DVec3) -> Self {
         Vertex {
             position: DVec4::new(position.x, position.y, position.z, 1.0),
             uv,
+            normal: normal.normalize_or_zero(),
+            color: DVec4::ONE,
+            tangent: DVec4::ZERO,
         }
     }
 }
@@ -177,4 +266,34 @@ impl Vertex {
     pub fn uv(&self) -> &DVec2 {
         &self.uv
     }
+    /// Exposes a reference to the normal of the vertex.
+    pub fn normal(&self) -> &DVec3 {
+        &self.normal
+    }
+    /// Exposes a reference to the color of the vertex.
+    pub fn color(&self) -> &DVec4 {
+        &self.color
+    }
+    /// Sets the color of the vertex.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - The new color (RGBA) of the vertex. Each channel is a value between 0-1. It
+    ///   multiplies the color of the mesh's material.
+    pub fn set_color(&mut self, color: DVec4) {
+        self.color = color;
+    }
+    /// Exposes a reference to the tangent of the vertex.
+    pub fn tangent(&self) -> &DVec4 {
+        &self.tangent
+    }
+    /// Sets the tangent of the vertex.
+    ///
+    /// # Arguments
+    ///
+    /// * `tangent` - The tangent of the surface at the vertex (xyz, normalized), and the sign of
+    ///   its bitangent (w). See [`Mesh::generate_tangents`].
+    pub fn set_tangent(&mut self, tangent: DVec4) {
+        self.tangent = tangent;
+    }
 }
@@ -2,3 +2,5 @@
 pub mod loaders;
 pub mod texture;
 pub mod mesh;
+pub mod material;
+pub mod sampler;
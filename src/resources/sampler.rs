@@ -0,0 +1,236 @@
+//! Contains everything pertaining to how textures are read.
+use glam::{DVec2, DVec4};
+
+use super::texture::Texture;
+
+/// Defines how the color of a texture is obtained at some uv coordinates.
+#[derive(Clone, Copy, Debug)]
+pub struct Sampler {
+    /// How the texels around the uv coordinates are combined.
+    pub filter: Filter,
+    /// How the mipmap levels of the texture are used when it is minified.
+    pub mip_filter: MipFilter,
+    /// How u coordinates outside of the 0-1 range are handled.
+    pub wrap_u: WrapMode,
+    /// How v coordinates outside of the 0-1 range are handled.
+    pub wrap_v: WrapMode,
+    /// Color (RGBA) outside of the texture when using [`WrapMode::ClampToBorder`]. Each channel
+    /// is a value between 0-1.
+    pub border_color: DVec4,
+}
+impl Sampler {
+    /// Creates a sampler which blends between the mipmap levels of the textures and repeats
+    /// them in both directions.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - How the texels around the uv coordinates are combined.
+    pub fn new(filter: Filter) -> Self {
+        Sampler {
+            filter,
+            mip_filter: MipFilter::Linear,
+            wrap_u: WrapMode::Repeat,
+            wrap_v: WrapMode::Repeat,
+            border_color: DVec4::ZERO,
+        }
+    }
+    /// Obtains the color of a texture at some uv coordinates.
+    ///
+    /// Uv coordinates beyond the 0-1 range are handled according to the wrap modes. The mipmap
+    /// level is chosen so that a pixel on screen covers about one texel.
+    ///
+    /// # Arguments
+    ///
+    /// * `texture` - The texture to sample.
+    /// * `uv` - Where to sample the texture. (0, 0) is the top left corner of the texture, and
+    ///   (1, 1) the bottom right one.
+    /// * `uv_dx` - How much the uv coordinates change when moving one pixel right on screen.
+    /// * `uv_dy` - How much the uv coordinates change when moving one pixel down on screen. Zero
+    ///   derivatives always sample the full size texture.
+    ///
+    /// # Return
+    ///
+    /// The color (RGBA) of the texture, with each channel between 0-1.
+    pub fn sample(&self, texture: &Texture, uv: DVec2, uv_dx: DVec2, uv_dy: DVec2) -> DVec4 {
+        // Find the level of detail from the number of texels covered by a pixel.
+        let size = DVec2::new(texture.width() as f64, texture.height() as f64);
+        let footprint = (uv_dx * size).length().max((uv_dy * size).length());
+        let max_level = (texture.nb_levels() - 1) as f64;
+        let lod = footprint.log2().clamp(0.0, max_level);
+        match self.mip_filter {
+            MipFilter::None => self.sample_level(texture, 0, uv),
+            MipFilter::Nearest => self.sample_level(texture, lod.round() as usize, uv),
+            MipFilter::Linear => {
+                let level = lod.floor();
+                let color = self.sample_level(texture, level as usize, uv);
+                if lod > level {
+                    color.lerp(
+                        self.sample_level(texture, level as usize + 1, uv),
+                        lod - level,
+                    )
+                } else {
+                    color
+                }
+            }
+        }
+    }
+    /// Obtains the color of a single mipmap level of a texture at some uv coordinates.
+    ///
+    /// # Arguments
+    ///
+    /// * `texture` - The texture to sample.
+    /// * `level` - The mipmap level to sample. 0 is the full size texture.
+    /// * `uv` - Where to sample the texture.
+    fn sample_level(&self, texture: &Texture, level: usize, uv: DVec2) -> DVec4 {
+        let (width, height) = texture.level_size(level);
+        // Position of the uv coordinates in texels.
+        let position = uv * DVec2::new(width as f64, height as f64);
+        let texel = |x: i64, y: i64| {
+            match (self.wrap_u.wrap(x, width), self.wrap_v.wrap(y, height)) {
+                (Some(x), Some(y)) => texture.texel(level, x, y),
+                _ => self.border_color,
+            }
+        };
+        match self.filter {
+            Filter::Nearest => {
+                let nearest = position.floor();
+                texel(nearest.x as i64, nearest.y as i64)
+            }
+            Filter::Bilinear => {
+                // The colors are defined at the center of the texels.
+                let position = position - 0.5;
+                let top_left = position.floor();
+                let t = position - top_left;
+                let (x, y) = (top_left.x as i64, top_left.y as i64);
+                let top = texel(x, y).lerp(texel(x + 1, y), t.x);
+                let bottom = texel(x, y + 1).lerp(texel(x + 1, y + 1), t.x);
+                top.lerp(bottom, t.y)
+            }
+        }
+    }
+}
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler::new(Filter::Nearest)
+    }
+}
+/// How texel coordinates outside of a texture are brought back inside of it.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WrapMode {
+    /// The texture is tiled.
+    Repeat,
+    /// The texture is tiled, with every other tile flipped so that the edges line up.
+    MirroredRepeat,
+    /// The edges of the texture are stretched outward.
+    ClampToEdge,
+    /// Everything outside of the texture has the sampler's border color.
+    ClampToBorder,
+}
+impl WrapMode {
+    /// Brings a texel coordinate along one axis inside the texture.
+    ///
+    /// # Arguments
+    ///
+    /// * `coordinate` - The texel coordinate, which can be negative or beyond the texture.
+    /// * `size` - Number of texels of the texture along the axis.
+    ///
+    /// # Return
+    ///
+    /// The coordinate inside the texture, or None if the border color has to be used instead.
+    fn wrap(&self, coordinate: i64, size: usize) -> Option<usize> {
+        let size = size as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => coordinate.rem_euclid(size),
+            WrapMode::MirroredRepeat => {
+                let period = coordinate.rem_euclid(2 * size);
+                if period < size {
+                    period
+                } else {
+                    2 * size - 1 - period
+                }
+            }
+            WrapMode::ClampToEdge => coordinate.clamp(0, size - 1),
+            WrapMode::ClampToBorder => {
+                if !(0..size).contains(&coordinate) {
+                    return None;
+                }
+                coordinate
+            }
+        };
+        Some(wrapped as usize)
+    }
+}
+/// How the mipmap levels of a texture are used.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MipFilter {
+    /// Always uses the full size texture. Minified textures shimmer and alias.
+    None,
+    /// Uses the closest mipmap level.
+    Nearest,
+    /// Blends the two closest mipmap levels. Combined with [`Filter::Bilinear`], this gives
+    /// trilinear filtering.
+    Linear,
+}
+/// How the texels around the sampled uv coordinates are combined.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Filter {
+    /// Uses the color of the closest texel. Textures look blocky when magnified.
+    Nearest,
+    /// Blends the colors of the 4 closest texels depending on how close they are. Textures look
+    /// smooth when magnified.
+    Bilinear,
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::texture::Format;
+
+    /// Texel coordinates the sampler computes for the tested u coordinates on a 4 texel wide
+    /// texture: -0.25, -1.0, 1.0 and 1e12.
+    const COORDINATES: [i64; 4] = [-1, -4, 4, 4_000_000_000_000];
+    const SIZE: usize = 4;
+
+    /// Samples a 4x1 texture whose texels have a red value of 0, 1, 2 and 3 respectively.
+    fn sampled_texel(wrap_mode: WrapMode, u: f64) -> usize {
+        let texture = Texture::from_pixels(
+            SIZE,
+            1,
+            &vec![0, 0, 0, 1, 0, 0, 2, 0, 0, 3, 0, 0],
+            Format::RGB24,
+        )
+        .unwrap();
+        let sampler = Sampler {
+            mip_filter: MipFilter::None,
+            wrap_u: wrap_mode,
+            ..Sampler::new(Filter::Nearest)
+        };
+        let color = sampler.sample(&texture, DVec2::new(u, 0.5), DVec2::ZERO, DVec2::ZERO);
+        (color.x * 255.0).round() as usize
+    }
+
+    #[test]
+    fn repeat_tiles_the_texture() {
+        let wrapped = COORDINATES.map(|coordinate| WrapMode::Repeat.wrap(coordinate, SIZE));
+        assert_eq!(wrapped, [Some(3), Some(0), Some(0), Some(0)]);
+    }
+
+    #[test]
+    fn mirrored_repeat_flips_every_other_tile() {
+        let wrapped = COORDINATES.map(|coordinate| WrapMode::MirroredRepeat.wrap(coordinate, SIZE));
+        assert_eq!(wrapped, [Some(0), Some(3), Some(3), Some(0)]);
+    }
+
+    #[test]
+    fn clamp_to_edge_stretches_the_edges() {
+        let wrapped = COORDINATES.map(|coordinate| WrapMode::ClampToEdge.wrap(coordinate, SIZE));
+        assert_eq!(wrapped, [Some(0), Some(0), Some(3), Some(3)]);
+    }
+
+    #[test]
+    fn sampling_wraps_uv_coordinates() {
+        let sample = |wrap_mode| [-0.25, -1.0, 1.0, 1e12].map(|u| sampled_texel(wrap_mode, u));
+        assert_eq!(sample(WrapMode::Repeat), [3, 0, 0, 0]);
+        assert_eq!(sample(WrapMode::MirroredRepeat), [0, 3, 3, 0]);
+        assert_eq!(sample(WrapMode::ClampToEdge), [0, 0, 3, 3]);
+    }
+}
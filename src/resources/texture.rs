@@ -2,6 +2,12 @@
 use core::fmt;
 use std::collections::HashMap;
 
+use glam::{DVec4, U8Vec4};
+
+use crate::graphics::color;
+
+use super::sampler::Sampler;
+
 /// Owns the textures as well as the necessary maps to efficiently access them.
 pub struct TextureCatalog {
     /// Id to give to the next texture added. Start at 1, and use 0 for issues.
@@ -25,15 +31,17 @@ impl TextureCatalog {
     /// # Arguments
     ///
     /// * `name` - The name of the texture.
-    /// * `texture` - The texture to add to the catalog.
+    /// * `texture` - The texture to add to the catalog. Its mipmaps are generated (see
+    ///   [`Texture::generate_mipmaps`]).
     ///
     /// # Return
     ///
     /// The id of the texture that was added to the catalog if succesful.
-    pub fn add_texture(&mut self, name: String, texture: Texture) -> Result<u32, TextureError> {
+    pub fn add_texture(&mut self, name: String, mut texture: Texture) -> Result<u32, TextureError> {
         if let Some(&id) = self.texture_ids.get(&name) {
             return Err(TextureError::TextureNameAlreadyExists { name: name, id: id });
         }
+        texture.generate_mipmaps();
         self.texture_ids.insert(name, self.next_id);
         self.textures.insert(self.next_id, texture);
         self.next_id += 1;
@@ -86,6 +94,11 @@ pub struct Texture {
     height: usize,
     /// Pixel format of the texture.
     format: Format,
+    /// How the texture is sampled, unless the material using it says otherwise.
+    sampler: Sampler,
+    /// Smaller versions of the texture, each half the size of the previous one, down to a single
+    /// pixel. Empty until [`Texture::generate_mipmaps`] is called.
+    mipmaps: Vec<MipLevel>,
 }
 impl Texture {
     /// Create a new black texture instance.
@@ -100,22 +113,18 @@ impl Texture {
     ///
     /// The new instance created through the function.
     pub fn new(width: usize, height: usize, format: Format) -> Self {
-        match format {
-            Format::RGBA32 => {
-                let pixel = [0, 0, 0, 255];
-                Texture {
-                    pixels: pixel.repeat(width * height),
-                    width,
-                    height,
-                    format,
-                }
-            }
-            Format::RGB24 => Texture {
-                pixels: vec![0; 3 * width * height],
-                width,
-                height,
-                format,
-            },
+        // Opaque black pixels.
+        let pixel: &[u8] = match format.nb_channels() {
+            4 => &[0, 0, 0, 255],
+            _ => &[0, 0, 0],
+        };
+        Texture {
+            pixels: pixel.repeat(width * height),
+            width,
+            height,
+            format,
+            sampler: Sampler::default(),
+            mipmaps: Vec::new(),
         }
     }
     /// Create a new user defined texture.
@@ -137,10 +146,7 @@ impl Texture {
         format: Format,
     ) -> Result<Self, TextureError> {
         // Check the number of channels that the format enforces.
-        let format_channels = match format {
-            Format::RGBA32 => 4,
-            Format::RGB24 => 3,
-        };
+        let format_channels = format.nb_channels();
         // Check if pixels has correct size given width, height
         // and the number of channels.
         if width * height * format_channels != pixels.len() {
@@ -154,26 +160,26 @@ impl Texture {
             width,
             height,
             format,
+            sampler: Sampler::default(),
+            mipmaps: Vec::new(),
         })
     }
     /// Obtain the pixel value of the texture given uv coordinates.
     ///
     /// # Arguments
     ///
-    /// * `u` - U coordinate of the texture. Values must be between 0 and 1 (included).
-    /// * `v` - V coordinate of the texture. Values must be between 0 and 1 (included).
+    /// * `u` - U coordinate of the texture. Values outside of 0-1 repeat the texture.
+    /// * `v` - V coordinate of the texture. Values outside of 0-1 repeat the texture.
     ///
     /// # Return
     ///
-    /// A slice of the texture representing the pixel at the UV coordinates.
+    /// A slice of the texture representing the pixel at the UV coordinates. The values are not
+    /// decoded, see [`Texture::texel`] for a linear color.
     #[inline(always)]
     pub fn from_uv(&self, u: f64, v: f64) -> &[u8] {
-        // Handles the wrapping.
-        let (u_fraction, v_fraction) = (u - u.trunc(), v - v.trunc());
-        let nb_channels = match self.format {
-            Format::RGBA32 => 4,
-            Format::RGB24 => 3,
-        };
+        // Handles the wrapping, negative coordinates included.
+        let (u_fraction, v_fraction) = (u.rem_euclid(1.0), v.rem_euclid(1.0));
+        let nb_channels = self.format.nb_channels();
 
         let mut x = (u_fraction * self.width as f64) as usize;
         let mut y = (v_fraction * self.height as f64) as usize;
@@ -185,12 +191,87 @@ impl Texture {
         let index = (x + y * self.width) * nb_channels;
         &self.pixels[index..index + nb_channels]
     }
+    /// Generates the mipmaps of the texture, replacing the existing ones.
+    ///
+    /// Each level averages blocks of 2x2 pixels from the previous one, until a level of a single
+    /// pixel is reached. The colors of sRGB textures are averaged in linear space, so the levels
+    /// do not get darker.
+    pub fn generate_mipmaps(&mut self) {
+        self.mipmaps.clear();
+        let nb_channels = self.nb_chanels() as usize;
+        let srgb = self.format.is_srgb();
+        let (mut width, mut height) = (self.width, self.height);
+        while width > 1 || height > 1 {
+            let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+            let source = self
+                .mipmaps
+                .last()
+                .map_or(&self.pixels, |level| &level.pixels);
+            let mut pixels = Vec::with_capacity(next_width * next_height * nb_channels);
+            for y in 0..next_height {
+                for x in 0..next_width {
+                    for channel in 0..nb_channels {
+                        // The block's values, repeating the last row/column of 1 pixel wide levels.
+                        let block = [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(dx, dy)| {
+                            let source_x = (2 * x + dx).min(width - 1);
+                            let source_y = (2 * y + dy).min(height - 1);
+                            source[(source_x + source_y * width) * nb_channels + channel]
+                        });
+                        // Alpha is never sRGB encoded.
+                        if srgb && channel < 3 {
+                            let sum: f64 =
+                                block.iter().map(|&value| color::decode_srgb(value)).sum();
+                            pixels.push(color::encode_srgb(sum / 4.0));
+                        } else {
+                            let sum: u32 = block.iter().map(|&value| value as u32).sum();
+                            pixels.push(((sum + 2) / 4) as u8);
+                        }
+                    }
+                }
+            }
+            self.mipmaps.push(MipLevel {
+                pixels,
+                width: next_width,
+                height: next_height,
+            });
+            (width, height) = (next_width, next_height);
+        }
+    }
+    /// Obtains the color of a single pixel of the texture.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - Mipmap level to read from. 0 is the full size texture.
+    /// * `x` - Column of the pixel. Must be smaller than the width of the level.
+    /// * `y` - Row of the pixel. Must be smaller than the height of the level.
+    ///
+    /// # Return
+    ///
+    /// The linear color (RGBA) of the pixel, with each channel between 0-1. Pixels are fully
+    /// opaque if the format has no alpha channel.
+    #[inline(always)]
+    pub fn texel(&self, level: usize, x: usize, y: usize) -> DVec4 {
+        let nb_channels = self.nb_chanels() as usize;
+        let (pixels, width) = match level {
+            0 => (&self.pixels, self.width),
+            _ => (
+                &self.mipmaps[level - 1].pixels,
+                self.mipmaps[level - 1].width,
+            ),
+        };
+        let index = (x + y * width) * nb_channels;
+        let pixel = &pixels[index..index + nb_channels];
+        let alpha = if nb_channels == 4 { pixel[3] } else { 255 };
+        let color = U8Vec4::new(pixel[0], pixel[1], pixel[2], alpha);
+        if self.format.is_srgb() {
+            color::decode_srgb_color(color)
+        } else {
+            color.as_dvec4() / 255.0
+        }
+    }
     /// Obtains the number of channels the format requires.
     pub fn nb_chanels(&self) -> u32 {
-        match self.format {
-            Format::RGBA32 => 4,
-            Format::RGB24 => 3,
-        }
+        self.format.nb_channels() as u32
     }
 }
 // Getters and setters.
@@ -211,14 +292,68 @@ impl Texture {
     pub fn format(&self) -> &Format {
         &self.format
     }
+    /// Obtains the number of mipmap levels, the full size texture included.
+    pub fn nb_levels(&self) -> usize {
+        1 + self.mipmaps.len()
+    }
+    /// Obtains the width and height (in pixels) of a mipmap level. 0 is the full size texture.
+    pub fn level_size(&self, level: usize) -> (usize, usize) {
+        match level {
+            0 => (self.width, self.height),
+            _ => (
+                self.mipmaps[level - 1].width,
+                self.mipmaps[level - 1].height,
+            ),
+        }
+    }
+    /// Obtains the sampler used to read the texture.
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+    /// Sets the sampler used to read the texture. Materials can override it (see
+    /// [`super::material::Material::sampler`]).
+    pub fn set_sampler(&mut self, sampler: Sampler) {
+        self.sampler = sampler;
+    }
+}
+/// A smaller version of a texture, used when the texture is seen from afar.
+struct MipLevel {
+    /// The pixel values, in the same format as the texture.
+    pixels: Vec<u8>,
+    /// Number of pixels horizontally.
+    width: usize,
+    /// Number of pixels vertically.
+    height: usize,
 }
 /// Format of the texture.
+///
+/// Textures holding colors (diffuse, specular, emissive) are usually sRGB encoded, while
+/// textures holding data (normal maps) are linear.
 #[derive(Copy, Clone)]
 pub enum Format {
-    /// 8 bits for red, green, blue and alpha channels, respectively.
+    /// 8 bits for red, green, blue and alpha channels, respectively. Linear values.
     RGBA32,
-    /// 8 bits for red, green, blue channels, respectively.
+    /// 8 bits for red, green, blue channels, respectively. Linear values.
     RGB24,
+    /// 8 bits for red, green, blue and alpha channels, respectively. The color channels are sRGB
+    /// encoded, alpha is linear.
+    SRGBA32,
+    /// 8 bits for red, green, blue channels, respectively. sRGB encoded.
+    SRGB24,
+}
+impl Format {
+    /// Obtains the number of channels of the format.
+    pub fn nb_channels(&self) -> usize {
+        match self {
+            Format::RGBA32 | Format::SRGBA32 => 4,
+            Format::RGB24 | Format::SRGB24 => 3,
+        }
+    }
+    /// Whether the color channels are sRGB encoded, and are decoded to linear values when
+    /// sampled.
+    pub fn is_srgb(&self) -> bool {
+        matches!(self, Format::SRGBA32 | Format::SRGB24)
+    }
 }
 /// List of error that can be thrown when using textures.
 #[derive(Debug)]
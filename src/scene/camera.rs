@@ -64,6 +64,10 @@ pub enum Projection {
     },
     /// This type of projection is depth invariant. Obejcts farther away do not seem smaller.
     Orthographic {
+        /// Distance to the near clipping plane of the view box.
+        near_clip: f32,
+        /// Distance to the far clipping plane of the view box.
+        far_clip: f32,
         /// Width of the orthographic projection (in meters).
         width: f32,
         /// Height of the orthographics projection (in meters).
@@ -124,6 +128,43 @@ impl Camera {
         c.update_transform();
         c
     }
+    /// Creates a new [`Camera`] that uses an orthographic projection.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position of the camera in world space.
+    /// * `quat` - Orientation of the camera.
+    /// * `near_clip` - Distance to the near clipping plane of the view box.
+    /// * `far_clip` - Distance to the far clipping plane of the view box.
+    /// * `width` - Width of the view box (in meters).
+    /// * `height` - Height of the view box (in meters).
+    /// * `camera_style` - How the camera behaves when moved with the mouse.
+    pub fn new_orthographic(
+        position: &DVec3,
+        quat: &DQuat,
+        near_clip: f32,
+        far_clip: f32,
+        width: f32,
+        height: f32,
+        camera_style: CameraStyle,
+    ) -> Self {
+        let orthographic = Projection::Orthographic {
+            near_clip,
+            far_clip,
+            width,
+            height,
+        };
+        let mut c = Camera {
+            position: *position,
+            quat: *quat,
+            transform: DMat4::IDENTITY,
+            projection: orthographic,
+            velocity: 500.0,
+            camera_style,
+        };
+        c.update_transform();
+        c
+    }
     /// Gets an immutable reference to the position vector.
     ///
     /// # Returns
@@ -232,6 +273,30 @@ impl Camera {
         // Update transformation matrix to reflect the changes.
         self.update_transform();
     }
+    /// Zooms the camera in or out.
+    ///
+    /// For an orthographic projection, the width and height of the view box are divided by
+    /// `factor`. For a perspective projection, the horizontal fov is narrowed such that objects
+    /// appear `factor` times bigger (the fov is kept between 1 and 179 degrees).
+    ///
+    /// # Arguments
+    ///
+    /// * `factor` - Values bigger than 1 zoom in, and values between 0 and 1 zoom out.
+    pub fn zoom(&mut self, factor: f64) {
+        if factor <= 0.0 {
+            return;
+        }
+        match &mut self.projection {
+            Projection::Perspective { hfov, .. } => {
+                let half_fov_tan = ((*hfov / 2.0).to_radians() as f64).tan() / factor;
+                *hfov = ((2.0 * half_fov_tan.atan()).to_degrees() as f32).clamp(1.0, 179.0);
+            }
+            Projection::Orthographic { width, height, .. } => {
+                *width /= factor as f32;
+                *height /= factor as f32;
+            }
+        }
+    }
     /// Adds to the velocity of the camera.
     ///
     /// Positive values increase velocity, and negative value decrease it.
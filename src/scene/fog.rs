@@ -0,0 +1,100 @@
+//! Fog hiding the parts of the scene far away from the camera.
+
+use glam::DVec3;
+
+/// Fog covering the scene. Surfaces fade into the color of the fog the further they are from
+/// the camera.
+#[derive(Clone, Copy, Debug)]
+pub struct Fog {
+    /// How the fog thickens with distance.
+    pub mode: FogMode,
+    /// Linear color (RGB) of the fog. Each channel is a value between 0-1. None uses the color
+    /// the pipeline clears the screen with, so that the fog blends in with the background.
+    pub color: Option<DVec3>,
+    /// Makes the fog thinner above a certain height. None gives the same fog everywhere.
+    pub height_falloff: Option<HeightFalloff>,
+}
+impl Fog {
+    /// Creates a fog with the color of the background and no height falloff.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - How the fog thickens with distance.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if `mode` is `FogMode::Linear` and its `end` is not greater than its
+    /// `start`.
+    pub fn new(mode: FogMode) -> Self {
+        if let FogMode::Linear { start, end } = mode {
+            debug_assert!(
+                end > start,
+                "Linear fog must end ({end}) further than it starts ({start})."
+            );
+        }
+        Fog {
+            mode,
+            color: None,
+            height_falloff: None,
+        }
+    }
+    /// Obtains how much a point is hidden by the fog.
+    ///
+    /// # Arguments
+    ///
+    /// * `distance` - Distance (in world units) from the camera to the point, along the view
+    ///   direction.
+    /// * `height` - Height (world space y) of the point.
+    ///
+    /// # Return
+    ///
+    /// A value between 0-1, where 1 means the point is fully hidden.
+    pub fn amount(&self, distance: f64, height: f64) -> f64 {
+        // The fog gets exponentially thinner above its height.
+        let thickness = self.height_falloff.map_or(1.0, |height_falloff| {
+            (-height_falloff.falloff * (height - height_falloff.height).max(0.0)).exp()
+        });
+        let distance = distance.max(0.0);
+        let amount = match self.mode {
+            FogMode::Linear { start, end } => {
+                thickness * ((distance - start) / (end - start).max(f64::EPSILON))
+            }
+            FogMode::Exponential { density } => 1.0 - (-density * thickness * distance).exp(),
+            FogMode::ExponentialSquared { density } => {
+                1.0 - (-(density * thickness * distance).powi(2)).exp()
+            }
+        };
+        amount.clamp(0.0, 1.0)
+    }
+}
+/// How fog thickens with distance.
+#[derive(Clone, Copy, Debug)]
+pub enum FogMode {
+    /// The fog goes linearly from nothing to fully opaque between two distances.
+    Linear {
+        /// Distance (in world units) where the fog starts.
+        start: f64,
+        /// Distance (in world units) where the fog hides everything. Must be greater than
+        /// `start`.
+        end: f64,
+    },
+    /// The fog thickens quickly close to the camera, then slower and slower.
+    Exponential {
+        /// How thick the fog is. Bigger values hide the scene closer to the camera.
+        density: f64,
+    },
+    /// The fog stays thin close to the camera, then thickens quickly.
+    ExponentialSquared {
+        /// How thick the fog is. Bigger values hide the scene closer to the camera.
+        density: f64,
+    },
+}
+/// Makes fog thinner with height, like mist settling in valleys.
+#[derive(Clone, Copy, Debug)]
+pub struct HeightFalloff {
+    /// Height (world space y) below which the fog has its full thickness.
+    pub height: f64,
+    /// How fast the fog thins out above `height`. The thickness is divided by e every
+    /// 1/`falloff` world units.
+    pub falloff: f64,
+}
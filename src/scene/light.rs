@@ -1,8 +1,9 @@
 //! Necessary values to light up the scene.
 
-use glam::{DVec3, U8Vec3};
+use glam::{DMat4, DVec3, U8Vec3};
 
 /// The light object.
+#[derive(Clone)]
 pub struct Light {
     /// Strength of the light that determines how much the color of the
     /// object will be illuminated. 0 means no light.
@@ -11,43 +12,216 @@ pub struct Light {
     pub color: U8Vec3,
     /// What type of light it is.
     pub light_type: LightType,
+    /// Whether the light follows the camera. When true, the positions and directions of the
+    /// `light_type` are defined in camera space instead of world space (the camera looks towards
+    /// -Z).
+    pub attached_to_camera: bool,
+    /// Whether the light casts shadows. Only lights of type `AtInfinity` and `Point` can.
+    pub casts_shadows: bool,
+    /// Number of pixels along each side of the shadow map (or of each of its six faces for
+    /// `Point` lights). Bigger maps give sharper shadows, but take longer to render.
+    pub shadow_map_size: usize,
+    /// Distance the lit points are pushed towards the light before checking whether they are in
+    /// the shadow, in pixels of the shadow map so that it does not depend on the size of the
+    /// scene. Bigger values prevent surfaces from shadowing themselves, but detach the shadows
+    /// from their casters.
+    pub shadow_bias: f64,
 }
 impl Light {
     /// Creates a light.
     pub fn new(strength: f64, color: U8Vec3, light_type: LightType) -> Self {
-        match light_type {
+        let light_type = match light_type {
             // Normalize direction for lights of type `AtInfinity`.
             LightType::AtInfinity(mut dir) => {
                 // Ensure direction is normalizable.
                 if dir.length() == 0.0 {
                     dir = DVec3::Z;
                 }
-                Light {
-                    strength: strength.max(0.0),
-                    color,
-                    light_type: LightType::AtInfinity(dir.normalize()),
-                }
+                LightType::AtInfinity(dir.normalize())
             }
-            // Ensure Strength is positive.
+            // Ensure the constant attenuation is positive.
             LightType::Point {
                 position,
                 constant,
                 linear,
                 quadratic,
-            } => Light {
-                strength: strength.max(0.0),
-                color,
-                light_type: LightType::Point {
+            } => LightType::Point {
+                position,
+                constant: constant.max(0.0),
+                linear,
+                quadratic,
+            },
+            // Normalize direction and ensure the cones are valid.
+            LightType::Spot {
+                position,
+                mut direction,
+                inner_angle,
+                outer_angle,
+                constant,
+                linear,
+                quadratic,
+            } => {
+                // Ensure direction is normalizable.
+                if direction.length() == 0.0 {
+                    direction = DVec3::NEG_Z;
+                }
+                let outer_angle = outer_angle.clamp(0.0, 90.0);
+                LightType::Spot {
                     position,
+                    direction: direction.normalize(),
+                    inner_angle: inner_angle.clamp(0.0, outer_angle),
+                    outer_angle,
                     constant: constant.max(0.0),
                     linear,
                     quadratic,
-                },
+                }
+            }
+        };
+        // Ensure Strength is positive.
+        Light {
+            strength: strength.max(0.0),
+            color,
+            light_type,
+            attached_to_camera: false,
+            casts_shadows: false,
+            shadow_map_size: 1024,
+            shadow_bias: 2.0,
+        }
+    }
+    /// Obtains how a point in space is lit by the light.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Position (in world space) of the point being lit.
+    ///
+    /// # Return
+    ///
+    /// The normalized direction from the point to the light, and the intensity of the light at
+    /// that point. Lights of type `Point` and `Spot` are attenuated with the distance (see
+    /// [`LightType::Point`]), and `Spot` lights also fade out between their inner and outer
+    /// cones.
+    pub fn illumination(&self, position: DVec3) -> (DVec3, f64) {
+        match self.light_type {
+            LightType::AtInfinity(dir) => (-dir, self.strength),
+            LightType::Point {
+                position: light_position,
+                constant,
+                linear,
+                quadratic,
+            } => {
+                let to_light = light_position - position;
+                let intensity =
+                    attenuate(self.strength, constant, linear, quadratic, to_light.length());
+                (to_light.normalize_or_zero(), intensity)
+            }
+            LightType::Spot {
+                position: light_position,
+                direction,
+                inner_angle,
+                outer_angle,
+                constant,
+                linear,
+                quadratic,
+            } => {
+                let to_light = light_position - position;
+                let to_light_normalized = to_light.normalize_or_zero();
+                // Find where the point is relative to the cones (1 inside the inner cone, 0
+                // outside the outer one).
+                let cos_theta = direction.dot(-to_light_normalized);
+                let cos_inner = (inner_angle as f64).to_radians().cos();
+                let cos_outer = (outer_angle as f64).to_radians().cos();
+                let cone = if cos_inner - cos_outer > 0.0 {
+                    ((cos_theta - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0)
+                } else if cos_theta >= cos_outer {
+                    1.0
+                } else {
+                    0.0
+                };
+                // Smoothstep for a softer edge.
+                let falloff = cone * cone * (3.0 - 2.0 * cone);
+                let intensity =
+                    attenuate(self.strength, constant, linear, quadratic, to_light.length());
+                (to_light_normalized, intensity * falloff)
+            }
+        }
+    }
+    /// Obtains the color of the light with each channel (RGB) between 0-1.
+    pub fn normalized_color(&self) -> DVec3 {
+        self.color.as_dvec3() / 255.0
+    }
+    /// Obtains the light as it is in world space.
+    ///
+    /// Lights attached to the camera are moved and rotated with the camera's transform. Other
+    /// lights are returned unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `camera_transform` - Transform that moves the camera from the world's origin to its
+    ///   position.
+    pub fn in_world_space(&self, camera_transform: &DMat4) -> Light {
+        if !self.attached_to_camera {
+            return self.clone();
+        }
+        let light_type = match self.light_type {
+            LightType::AtInfinity(dir) => {
+                LightType::AtInfinity(camera_transform.transform_vector3(dir).normalize())
+            }
+            LightType::Point {
+                position,
+                constant,
+                linear,
+                quadratic,
+            } => LightType::Point {
+                position: camera_transform.transform_point3(position),
+                constant,
+                linear,
+                quadratic,
+            },
+            LightType::Spot {
+                position,
+                direction,
+                inner_angle,
+                outer_angle,
+                constant,
+                linear,
+                quadratic,
+            } => LightType::Spot {
+                position: camera_transform.transform_point3(position),
+                direction: camera_transform.transform_vector3(direction).normalize(),
+                inner_angle,
+                outer_angle,
+                constant,
+                linear,
+                quadratic,
             },
+        };
+        Light {
+            light_type,
+            attached_to_camera: false,
+            ..self.clone()
         }
     }
 }
+/// Obtains the intensity of a light after being attenuated by the distance it travelled.
+///
+/// # Arguments
+///
+/// * `strength` - Strength of the light.
+/// * `constant` - Constant light attenuation value.
+/// * `linear` - Linear light attenuation value.
+/// * `quadratic` - Quadratic light attenuation value.
+/// * `d` - Distance travelled by the light.
+fn attenuate(strength: f64, constant: f32, linear: f32, quadratic: f32, d: f64) -> f64 {
+    let denominator = constant as f64 + linear as f64 * d + quadratic as f64 * d * d;
+    // A non positive denominator means the light is not attenuated at all.
+    if denominator > 0.0 {
+        strength / denominator
+    } else {
+        strength
+    }
+}
 /// How the scene will be light up.
+#[derive(Clone, Copy)]
 pub enum LightType {
     /// Light at infinity.
     ///
@@ -62,11 +236,13 @@ pub enum LightType {
     ///
     ///
     /// # Example usage
-    /// ```
+    /// ```ignore
     /// let d = (position - vertex.position).length();
     /// let att = 1.0 / (constant + linear * d + quadratic * d * d);
-    /// let intensity = (light.strength * att).clamp(0.0, 1.0);
+    /// let intensity = light.strength * att;
     /// ```
+    ///
+    /// This is what [`Light::illumination`] computes.
     Point {
         /// Position of the point light.
         position: DVec3,
@@ -77,4 +253,24 @@ pub enum LightType {
         /// Quadratic light attenuation value.
         quadratic: f32,
     },
+    /// Light at a point in space that only shines within a cone.
+    ///
+    /// The light is at full intensity within the inner cone, and smoothly fades out until the
+    /// outer cone. It is attenuated with the distance the same way `Point` lights are.
+    Spot {
+        /// Position of the spot light.
+        position: DVec3,
+        /// Direction the spot light is pointing towards.
+        direction: DVec3,
+        /// Angle between the direction and the edge of the inner cone (In degrees).
+        inner_angle: f32,
+        /// Angle between the direction and the edge of the outer cone (In degrees, at most 90).
+        outer_angle: f32,
+        /// Constant light attenuation value. Must be bigger than 0.
+        constant: f32,
+        /// Linear light attenuation value.
+        linear: f32,
+        /// Quadratic light attenuation value.
+        quadratic: f32,
+    },
 }
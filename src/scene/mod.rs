@@ -1,9 +1,11 @@
 //! Contains everything needed to render the environment.
 
 use camera::Camera;
+use fog::Fog;
 use light::Light;
+use skybox::Skybox;
 
-use crate::resources::{mesh::Mesh, texture::TextureCatalog};
+use crate::resources::{material::MaterialCatalog, mesh::Mesh, texture::TextureCatalog};
 
 /// Contains everything in the scene that will be rendered.
 pub struct Scene {
@@ -11,10 +13,18 @@ pub struct Scene {
     camera: Camera,
     /// The list of textures in use in the scene.
     texture_catalog: TextureCatalog,
+    /// The list of materials in use in the scene.
+    material_catalog: MaterialCatalog,
     /// A list of meshes inside the scene.
     meshes: Vec<Mesh>,
     /// A list of lights inside the scene.
     lights: Vec<Light>,
+    /// The fog covering the scene, if there is one.
+    fog: Option<Fog>,
+    /// The background surrounding the scene, if there is one.
+    skybox: Option<Skybox>,
+    /// What reflective materials reflect, if it differs from the skybox.
+    environment_map: Option<Skybox>,
 }
 impl Scene {
     /// Create new scene with default camera placement.
@@ -23,8 +33,12 @@ impl Scene {
         Scene {
             camera,
             texture_catalog: TextureCatalog::new(),
+            material_catalog: MaterialCatalog::new(),
             meshes: Vec::new(),
             lights: Vec::new(),
+            fog: None,
+            skybox: None,
+            environment_map: None,
         }
     }
     /// Create new scene with user defined camera.
@@ -32,8 +46,12 @@ impl Scene {
         Scene {
             camera,
             texture_catalog: TextureCatalog::new(),
+            material_catalog: MaterialCatalog::new(),
             meshes: Vec::new(),
             lights: Vec::new(),
+            fog: None,
+            skybox: None,
+            environment_map: None,
         }
     }
     /// Adds a mesh to the scene.
@@ -63,6 +81,14 @@ impl Scene {
     pub fn texture_catalog(&self) -> &TextureCatalog {
         &self.texture_catalog
     }
+    /// Mutable reference for the material catalog.
+    pub fn material_catalog_mut(&mut self) -> &mut MaterialCatalog {
+        &mut self.material_catalog
+    }
+    /// Reference for the material catalog.
+    pub fn material_catalog(&self) -> &MaterialCatalog {
+        &self.material_catalog
+    }
     /// Mutable reference for the mesh vector.
     pub fn meshes_mut(&mut self) -> &mut Vec<Mesh> {
         &mut self.meshes
@@ -79,6 +105,34 @@ impl Scene {
     pub fn lights(&self) -> &[Light] {
         &self.lights
     }
+    /// Reference for the fog, if there is one.
+    pub fn fog(&self) -> Option<&Fog> {
+        self.fog.as_ref()
+    }
+    /// Sets the fog covering the scene. None removes it.
+    pub fn set_fog(&mut self, fog: Option<Fog>) {
+        self.fog = fog;
+    }
+    /// Reference for the skybox, if there is one.
+    pub fn skybox(&self) -> Option<&Skybox> {
+        self.skybox.as_ref()
+    }
+    /// Sets the background surrounding the scene. None leaves the color the pipeline clears the
+    /// screen with.
+    pub fn set_skybox(&mut self, skybox: Option<Skybox>) {
+        self.skybox = skybox;
+    }
+    /// Reference for the environment map, if there is one.
+    pub fn environment_map(&self) -> Option<&Skybox> {
+        self.environment_map.as_ref()
+    }
+    /// Sets what reflective materials (see [`crate::resources::material::Material::reflectivity`])
+    /// reflect. None reflects the skybox, if there is one.
+    pub fn set_environment_map(&mut self, environment_map: Option<Skybox>) {
+        self.environment_map = environment_map;
+    }
 }
 pub mod camera;
+pub mod fog;
 pub mod light;
+pub mod skybox;
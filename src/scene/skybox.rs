@@ -0,0 +1,130 @@
+//! The background surrounding the scene, infinitely far away.
+
+use std::f64::consts::PI;
+
+use glam::{DVec2, DVec3, Vec4Swizzles};
+
+use crate::resources::{sampler::WrapMode, texture::TextureCatalog};
+
+/// Background drawn wherever no mesh is, as if it were infinitely far away.
+///
+/// The textures are referenced through their id in the [`TextureCatalog`] of the scene.
+#[derive(Clone, Copy, Debug)]
+pub enum Skybox {
+    /// Six textures covering the faces of a cube around the camera, following the OpenGL
+    /// convention so that skyboxes made for it display correctly.
+    CubeMap {
+        /// The texture of each face, in the order +X, -X, +Y, -Y, +Z, -Z.
+        faces: [u32; 6],
+    },
+    /// A single texture wrapped around the camera, with the full 360° horizontally and 180°
+    /// vertically. The center of the texture is towards -Z.
+    Equirectangular {
+        /// The texture.
+        texture: u32,
+    },
+    /// A procedural sky, going from one color at the horizon to another straight up, and to a
+    /// third straight down. Each color is linear (RGB), with each channel between 0-1.
+    Gradient {
+        /// Color straight up.
+        zenith: DVec3,
+        /// Color at the horizon.
+        horizon: DVec3,
+        /// Color straight down.
+        ground: DVec3,
+    },
+}
+impl Skybox {
+    /// Obtains the color of the sky in a direction.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction` - Direction (in world space) from the camera. Does not need to be
+    ///   normalized.
+    /// * `textures` - The textures the skybox references.
+    ///
+    /// # Return
+    ///
+    /// The linear color (RGB) of the sky. Black if the direction is zero or a texture is missing.
+    pub fn color(&self, direction: DVec3, textures: &TextureCatalog) -> DVec3 {
+        let direction = direction.normalize_or_zero();
+        if direction == DVec3::ZERO {
+            return DVec3::ZERO;
+        }
+        match self {
+            Skybox::CubeMap { faces } => {
+                let (face, uv) = cube_map_uv(direction);
+                // The edges of the faces are not repeated, so that they line up.
+                sample(textures, faces[face], uv, WrapMode::ClampToEdge)
+            }
+            Skybox::Equirectangular { texture } => {
+                let uv = DVec2::new(
+                    0.5 + direction.x.atan2(-direction.z) / (2.0 * PI),
+                    direction.y.clamp(-1.0, 1.0).acos() / PI,
+                );
+                sample(textures, *texture, uv, WrapMode::Repeat)
+            }
+            Skybox::Gradient {
+                zenith,
+                horizon,
+                ground,
+            } => {
+                if direction.y >= 0.0 {
+                    horizon.lerp(*zenith, direction.y)
+                } else {
+                    horizon.lerp(*ground, -direction.y)
+                }
+            }
+        }
+    }
+}
+/// Obtains the face of a cube map a direction points to, and where on that face.
+///
+/// # Arguments
+///
+/// * `direction` - The direction (normalized).
+///
+/// # Return
+///
+/// The index of the face (in the order +X, -X, +Y, -Y, +Z, -Z) and the uv coordinates on it.
+fn cube_map_uv(direction: DVec3) -> (usize, DVec2) {
+    let abs = direction.abs();
+    let DVec3 { x, y, z } = direction;
+    // The face, the coordinates along it and the coordinate towards it.
+    let (face, coordinates, major) = if abs.x >= abs.y && abs.x >= abs.z {
+        if x >= 0.0 {
+            (0, DVec2::new(-z, -y), abs.x)
+        } else {
+            (1, DVec2::new(z, -y), abs.x)
+        }
+    } else if abs.y >= abs.z {
+        if y >= 0.0 {
+            (2, DVec2::new(x, z), abs.y)
+        } else {
+            (3, DVec2::new(x, -z), abs.y)
+        }
+    } else if z >= 0.0 {
+        (4, DVec2::new(x, -y), abs.z)
+    } else {
+        (5, DVec2::new(-x, -y), abs.z)
+    };
+    (face, (coordinates / major + 1.0) / 2.0)
+}
+/// Samples a texture of the skybox with its own sampler, on its full size level.
+///
+/// # Arguments
+///
+/// * `textures` - The textures the skybox references.
+/// * `id` - Id of the texture.
+/// * `uv` - UV coordinates on the texture.
+/// * `wrap_u` - How u coordinates outside of the 0-1 range are handled. v coordinates are always
+///   clamped, since the sky does not go past its poles.
+fn sample(textures: &TextureCatalog, id: u32, uv: DVec2, wrap_u: WrapMode) -> DVec3 {
+    let Some(texture) = textures.texture_from_id(id) else {
+        return DVec3::ZERO;
+    };
+    let mut sampler = *texture.sampler();
+    sampler.wrap_u = wrap_u;
+    sampler.wrap_v = WrapMode::ClampToEdge;
+    sampler.sample(texture, uv, DVec2::ZERO, DVec2::ZERO).xyz()
+}